@@ -1,24 +1,80 @@
 use std::error::Error;
+use std::fmt;
 use std::str::FromStr;
 
-pub fn puzzle(input: &str) -> i32 {
-    let program: Vec<Instruction> = input.lines()
-                                         .map(|line| line.trim())
-                                         //.inspect(|x| println!("about to parse: {:?}", x))
-                                         .map(|line| line.parse().expect("Parse error"))
-                                         //.inspect(|x| println!("finished parsing: {:?}", x))
-                                         .collect();
+pub mod optimise;
+
+pub fn puzzle(input: &str) -> Result<i32, MachineError> {
+    let program = parse_program(input)?;
     let mut machine = Machine::default();
-    machine.execute(&program);
-    machine.value_of(Register::A)
+    machine.execute(&optimise::optimise(&program))?;
+    Ok(machine.value_of(Register::A))
+}
+
+/// Parse one instruction per line, reporting the first line that doesn't parse.
+pub fn parse_program(input: &str) -> Result<Vec<Instruction>, MachineError> {
+    input.lines()
+         .map(|line| line.trim())
+         .enumerate()
+         .map(|(i, line)| {
+             line.parse::<Instruction>().map_err(|e| {
+                 MachineError::Parse {
+                     line: i + 1,
+                     message: e.to_string(),
+                 }
+             })
+         })
+         .collect()
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum Instruction {
-    Copy(FromLocation, Register),
-    Increment(Register),
-    Decrement(Register),
-    JumpNonZero(FromLocation, i32),
+    Copy(FromLocation, FromLocation),
+    Increment(FromLocation),
+    Decrement(FromLocation),
+    JumpNonZero(FromLocation, FromLocation),
+    /// Toggle the instruction this many instructions away.
+    Toggle(FromLocation),
+    /// Transmit a value on the output stream.
+    Out(FromLocation),
+    /// `jnz c -2` closing a loop that counts `c` (the first register) down and adds the value
+    /// (1 or -1) to the second register each time round. When `c` is positive the remaining
+    /// iterations are done at once; otherwise it jumps like the `jnz`. Only produced by the
+    /// optimiser.
+    AddLoop(Register, Register, i32),
+    /// `jnz d -5` closing `cpy x c; <add loop on c>; dec d`, given `x`, `c`, `d` and the add
+    /// loop's target. When `x` and `d` are both positive the remaining iterations are done at
+    /// once; otherwise it jumps like the `jnz`. Only produced by the optimiser.
+    MultiplyLoop(FromLocation, Register, Register, Register),
+}
+
+impl Instruction {
+    /// The instruction `tgl` turns this one into.
+    ///
+    /// One-argument instructions become `dec` if they were `inc`, and `inc` otherwise.
+    /// Two-argument instructions become `cpy` if they were `jnz`, and `jnz` otherwise.
+    pub fn toggled(self) -> Instruction {
+        use Instruction::*;
+        match self {
+            Increment(x) => Decrement(x),
+            Decrement(x) | Toggle(x) | Out(x) => Increment(x),
+            JumpNonZero(x, y) => Copy(x, y),
+            Copy(x, y) => JumpNonZero(x, y),
+            // The optimiser leaves programs that can toggle alone.
+            AddLoop(..) | MultiplyLoop(..) => self,
+        }
+    }
+
+    /// Whether every operand the instruction writes to is a register.
+    ///
+    /// Toggling can produce instructions like `cpy 1 2`, which are skipped when executed.
+    pub fn is_valid(&self) -> bool {
+        use Instruction::*;
+        !matches!(*self,
+                  Copy(_, FromLocation::Integer(_)) |
+                  Increment(FromLocation::Integer(_)) |
+                  Decrement(FromLocation::Integer(_)))
+    }
 }
 
 impl FromStr for Instruction {
@@ -52,13 +108,23 @@ impl FromStr for Instruction {
                                                 .ok_or("Offset not found")?
                                                 .parse()?))
             }
+            Some("tgl") => {
+                Ok(Instruction::Toggle(words.next()
+                                           .ok_or("Offset not found")?
+                                           .parse()?))
+            }
+            Some("out") => {
+                Ok(Instruction::Out(words.next()
+                                        .ok_or("FromLocation not found")?
+                                        .parse()?))
+            }
             Some(other) => Err(format!("Unknown instruction: {}", other).into()),
             None => Err("Instruction missing".into()),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum FromLocation {
     Integer(i32),
     Register(Register),
@@ -71,7 +137,13 @@ impl FromStr for FromLocation {
     }
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+impl From<Register> for FromLocation {
+    fn from(register: Register) -> Self {
+        FromLocation::Register(register)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum Register {
     A,
     B,
@@ -93,45 +165,240 @@ impl FromStr for Register {
     }
 }
 
-#[derive(Debug, Default)]
+/// Why a program couldn't be parsed or run. Line numbers start at 1.
+#[derive(Debug, PartialEq, Clone)]
+pub enum MachineError {
+    /// The line isn't a valid assembunny instruction.
+    Parse { line: usize, message: String },
+    /// The instruction writes to something that isn't a register.
+    InvalidOperand {
+        line: usize,
+        instruction: Instruction,
+    },
+    /// The instruction jumps to before the start of the program.
+    JumpOutOfRange { line: usize, offset: i32 },
+    /// The machine executed its whole instruction budget without halting.
+    BudgetExhausted { steps: u64 },
+}
+
+impl fmt::Display for MachineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MachineError::Parse { line, ref message } => {
+                write!(f, "Line {}: {}", line, message)
+            }
+            MachineError::InvalidOperand { line, instruction } => {
+                write!(f, "Line {}: invalid operand in {:?}", line, instruction)
+            }
+            MachineError::JumpOutOfRange { line, offset } => {
+                write!(f, "Line {}: jump by {} leaves the program", line, offset)
+            }
+            MachineError::BudgetExhausted { steps } => {
+                write!(f, "Still running after {} instructions", steps)
+            }
+        }
+    }
+}
+
+impl Error for MachineError {
+    fn description(&self) -> &str {
+        "assembunny machine error"
+    }
+}
+
+/// The state of a [`Machine`] at some point in its run.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Snapshot {
+    registers: [i32; 4],
+    prog_count: usize,
+    program: Vec<Instruction>,
+}
+
+#[derive(Debug, Default, Clone)]
 pub struct Machine {
     registers: [i32; 4],
     program: Vec<Instruction>,
     prog_count: usize,
+    output: Vec<i32>,
+    steps: u64,
+    budget: Option<u64>,
 }
 
 impl Machine {
-    fn execute(&mut self, instructions: &[Instruction]) {
+    /// A machine that gives up after executing `budget` instructions.
+    pub fn with_budget(budget: u64) -> Self {
+        Machine { budget: Some(budget), ..Machine::default() }
+    }
+
+    /// Everything transmitted by `out` instructions so far.
+    pub fn output(&self) -> &[i32] {
+        &self.output
+    }
+
+    /// Number of instructions executed so far.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Append `instructions` to the program and run until the program counter leaves it.
+    pub fn execute(&mut self, instructions: &[Instruction]) -> Result<(), MachineError> {
+        self.load(instructions)?;
+        self.run_until(|_| false)?;
+        Ok(())
+    }
+
+    /// Append `instructions` to the program without running anything.
+    pub fn load(&mut self, instructions: &[Instruction]) -> Result<(), MachineError> {
+        for (i, instruction) in instructions.iter().enumerate() {
+            if !instruction.is_valid() {
+                return Err(MachineError::InvalidOperand {
+                    line: self.program.len() + i + 1,
+                    instruction: *instruction,
+                });
+            }
+        }
         self.program.extend_from_slice(instructions);
+        Ok(())
+    }
 
-        while self.prog_count < self.program.len() {
-            //println!("Machine: Program Count: {:?}, Registers: {:?}",
-            //         self.prog_count, self.registers);
-            match self.program[self.prog_count] {
-                Instruction::Copy(FromLocation::Integer(from), to) => {
-                    self.registers[Machine::register_index(to)] = from;
-                }
-                Instruction::Copy(FromLocation::Register(from), to) => {
-                    self.registers[Machine::register_index(to)] = self.registers
-                        [Machine::register_index(from)];
+    /// Step until `predicate` holds, checking it before every instruction.
+    ///
+    /// Returns `true` if the predicate matched, or `false` if the program finished first.
+    pub fn run_until<F>(&mut self, mut predicate: F) -> Result<bool, MachineError>
+        where F: FnMut(&Machine) -> bool
+    {
+        loop {
+            if predicate(self) {
+                return Ok(true);
+            }
+            if !self.step()? {
+                return Ok(false);
+            }
+        }
+    }
+
+    /// Execute the instruction at the program counter.
+    ///
+    /// Returns `false` without doing anything if the program counter has left the program.
+    pub fn step(&mut self) -> Result<bool, MachineError> {
+        if self.is_halted() {
+            return Ok(false);
+        }
+        if self.budget.is_some_and(|budget| self.steps >= budget) {
+            return Err(MachineError::BudgetExhausted { steps: self.steps });
+        }
+        self.steps += 1;
+        //println!("Machine: Program Count: {:?}, Registers: {:?}",
+        //         self.prog_count, self.registers);
+        match self.program[self.prog_count] {
+            Instruction::Copy(from, FromLocation::Register(to)) => {
+                self.registers[Machine::register_index(to)] = self.resolve(from);
+            }
+            Instruction::Increment(FromLocation::Register(register)) => {
+                self.registers[Machine::register_index(register)] += 1;
+            }
+            Instruction::Decrement(FromLocation::Register(register)) => {
+                self.registers[Machine::register_index(register)] -= 1;
+            }
+            Instruction::JumpNonZero(condition, offset) => {
+                if self.resolve(condition) != 0 {
+                    let offset = self.resolve(offset);
+                    self.jump(offset)?;
+                    return Ok(true);
                 }
-                Instruction::Increment(register) => {
-                    self.registers[Machine::register_index(register)] += 1;
+            }
+            Instruction::Toggle(offset) => {
+                let target = self.prog_count as i64 + self.resolve(offset) as i64;
+                if target >= 0 && (target as usize) < self.program.len() {
+                    let target = target as usize;
+                    self.program[target] = self.program[target].toggled();
                 }
-                Instruction::Decrement(register) => {
-                    self.registers[Machine::register_index(register)] -= 1;
+            }
+            Instruction::Out(value) => {
+                let value = self.resolve(value);
+                self.output.push(value);
+            }
+            Instruction::AddLoop(counter, target, delta) => {
+                let count = self.value_of(counter);
+                if count > 0 {
+                    self.registers[Machine::register_index(target)] += delta * count;
+                    self.registers[Machine::register_index(counter)] = 0;
+                } else if count != 0 {
+                    self.jump(-2)?;
+                    return Ok(true);
                 }
-                Instruction::JumpNonZero(condition, offset) => {
-                    let condition = match condition {
-                        FromLocation::Integer(x) => x,
-                        FromLocation::Register(x) => self.registers[Machine::register_index(x)],
-                    };
-                    if condition != 0 {
-                        self.prog_count = (self.prog_count as i32 + offset - 1) as usize;
-                    }
+            }
+            Instruction::MultiplyLoop(x, inner, outer, target) => {
+                let (x, count) = (self.resolve(x), self.value_of(outer));
+                if x > 0 && count > 0 {
+                    self.registers[Machine::register_index(target)] += x * count;
+                    self.registers[Machine::register_index(inner)] = 0;
+                    self.registers[Machine::register_index(outer)] = 0;
+                } else if count != 0 {
+                    self.jump(-5)?;
+                    return Ok(true);
                 }
             }
-            self.prog_count += 1;
+            // Only reachable through toggling, and skipped.
+            Instruction::Copy(_, FromLocation::Integer(_)) |
+            Instruction::Increment(FromLocation::Integer(_)) |
+            Instruction::Decrement(FromLocation::Integer(_)) => {}
+        }
+        self.prog_count += 1;
+        Ok(true)
+    }
+
+    /// Whether the program counter has left the program.
+    pub fn is_halted(&self) -> bool {
+        self.prog_count >= self.program.len()
+    }
+
+    /// Index of the next instruction to execute.
+    pub fn prog_count(&self) -> usize {
+        self.prog_count
+    }
+
+    /// The values of registers `a` to `d`.
+    pub fn registers(&self) -> [i32; 4] {
+        self.registers
+    }
+
+    /// Save the registers, program counter and program (which `tgl` can change).
+    ///
+    /// Snapshots can be compared or hashed to spot a machine returning to an earlier state. The
+    /// output and the step count aren't part of the snapshot.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            registers: self.registers,
+            prog_count: self.prog_count,
+            program: self.program.clone(),
+        }
+    }
+
+    /// Go back to the state saved in `snapshot`.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.registers = snapshot.registers;
+        self.prog_count = snapshot.prog_count;
+        self.program = snapshot.program.clone();
+    }
+
+    /// Move the program counter by `offset`, which mustn't take it before the start.
+    fn jump(&mut self, offset: i32) -> Result<(), MachineError> {
+        let target = self.prog_count as i64 + offset as i64;
+        if target < 0 {
+            return Err(MachineError::JumpOutOfRange {
+                line: self.prog_count + 1,
+                offset,
+            });
+        }
+        self.prog_count = target as usize;
+        Ok(())
+    }
+
+    fn resolve(&self, location: FromLocation) -> i32 {
+        match location {
+            FromLocation::Integer(x) => x,
+            FromLocation::Register(x) => self.registers[Machine::register_index(x)],
         }
     }
 
@@ -145,7 +412,7 @@ impl Machine {
         }
     }
 
-    fn value_of(&self, register: Register) -> i32 {
+    pub fn value_of(&self, register: Register) -> i32 {
         self.registers[Machine::register_index(register)]
     }
 }
@@ -162,72 +429,160 @@ mod test {
     #[test]
     fn run_cpy() {
         let mut machine = Machine::default();
-        machine.execute(&[Instruction::Copy(FromLocation::Integer(5), Register::A)]);
+        machine.execute(&[Instruction::Copy(FromLocation::Integer(5), Register::A.into())])
+               .unwrap();
         assert_eq!(machine.value_of(Register::A), 5);
-        machine.execute(&[Instruction::Copy(FromLocation::Register(Register::A), Register::D)]);
+        machine.execute(&[Instruction::Copy(FromLocation::Register(Register::A),
+                                            Register::D.into())])
+               .unwrap();
         assert_eq!(machine.value_of(Register::D), 5);
     }
 
     #[test]
     fn run_inc_dec() {
         let mut machine = Machine::default();
-        machine.execute(&[Instruction::Decrement(Register::B)]);
+        machine.execute(&[Instruction::Decrement(Register::B.into())]).unwrap();
         assert_eq!(machine.value_of(Register::B), -1);
 
-        machine.execute(&[Instruction::Copy(FromLocation::Integer(5), Register::B)]);
-        machine.execute(&[Instruction::Increment(Register::B)]);
+        machine.execute(&[Instruction::Copy(FromLocation::Integer(5), Register::B.into())])
+               .unwrap();
+        machine.execute(&[Instruction::Increment(Register::B.into())]).unwrap();
         assert_eq!(machine.value_of(Register::B), 6);
     }
 
     #[test]
     fn run_jnz_jump_of_zero() {
-        let mut machine = Machine::default();
-        machine.execute(&[Instruction::Increment(Register::A),
-                          Instruction::Increment(Register::D),
-                          Instruction::JumpNonZero(FromLocation::Register(Register::D), 0),
-                          Instruction::Increment(Register::A)]);
-        assert_eq!(machine.value_of(Register::A), 2);
+        // Jumping by zero loops forever, so stop after a few hundred steps.
+        let mut machine = Machine::with_budget(100);
+        let result = machine.execute(&[Instruction::Increment(Register::A.into()),
+                                       Instruction::Increment(Register::D.into()),
+                                       Instruction::JumpNonZero(FromLocation::Register(Register::D),
+                                                                FromLocation::Integer(0)),
+                                       Instruction::Increment(Register::A.into())]);
+        assert_eq!(result, Err(MachineError::BudgetExhausted { steps: 100 }));
+        assert_eq!(machine.value_of(Register::A), 1);
     }
 
     #[test]
     fn run_jnz_zero_condition_jump() {
         let mut machine = Machine::default();
-        machine.execute(&[Instruction::Increment(Register::A),
-                          Instruction::JumpNonZero(FromLocation::Register(Register::D), -1),
-                          Instruction::Increment(Register::A)]);
+        machine.execute(&[Instruction::Increment(Register::A.into()),
+                          Instruction::JumpNonZero(FromLocation::Register(Register::D),
+                                                   FromLocation::Integer(-1)),
+                          Instruction::Increment(Register::A.into())]).unwrap();
         assert_eq!(machine.value_of(Register::A), 2);
     }
 
     #[test]
     fn run_jnz_jump_zero() {
+        let mut machine = Machine::with_budget(100);
+        let result = machine.execute(&[Instruction::Increment(Register::C.into()),
+                                       Instruction::JumpNonZero(FromLocation::Register(Register::C),
+                                                                FromLocation::Integer(0)),
+                                       Instruction::Increment(Register::D.into()),
+                                       Instruction::Increment(Register::C.into())]);
+        assert_eq!(result, Err(MachineError::BudgetExhausted { steps: 100 }));
+        assert_eq!(machine.value_of(Register::C), 1);
+        assert_eq!(machine.value_of(Register::D), 0);
+    }
+
+    #[test]
+    fn run_jnz_forward_jump_one() {
         let mut machine = Machine::default();
-        machine.execute(&[Instruction::Increment(Register::C),
-                          Instruction::JumpNonZero(FromLocation::Register(Register::C), 0),
-                          Instruction::Increment(Register::D),
-                          Instruction::Increment(Register::C)]);
+        machine.execute(&[Instruction::Increment(Register::C.into()),
+                          Instruction::JumpNonZero(FromLocation::Register(Register::C),
+                                                   FromLocation::Integer(1)),
+                          Instruction::Increment(Register::D.into()),
+                          Instruction::Increment(Register::C.into())]).unwrap();
         assert_eq!(machine.value_of(Register::C), 2);
         assert_eq!(machine.value_of(Register::D), 1);
     }
 
     #[test]
-    fn run_jnz_forward_jump_one() {
+    fn run_jnz_forward_jump_two() {
         let mut machine = Machine::default();
-        machine.execute(&[Instruction::Increment(Register::C),
-                          Instruction::JumpNonZero(FromLocation::Register(Register::C), 1),
-                          Instruction::Increment(Register::D),
-                          Instruction::Increment(Register::C)]);
+        machine.execute(&[Instruction::Increment(Register::C.into()),
+                          Instruction::JumpNonZero(FromLocation::Register(Register::C),
+                                                   FromLocation::Integer(2)),
+                          Instruction::Increment(Register::D.into()),
+                          Instruction::Increment(Register::C.into())]).unwrap();
         assert_eq!(machine.value_of(Register::C), 2);
         assert_eq!(machine.value_of(Register::D), 0);
     }
 
     #[test]
-    fn run_jnz_forward_jump_two() {
+    fn run_tgl() {
+        let program = parse_program("cpy 2 a
+                                     tgl a
+                                     tgl a
+                                     tgl a
+                                     cpy 1 a
+                                     dec a
+                                     dec a")
+            .unwrap();
         let mut machine = Machine::default();
-        machine.execute(&[Instruction::Increment(Register::C),
-                          Instruction::JumpNonZero(FromLocation::Register(Register::C), 2),
-                          Instruction::Increment(Register::D),
-                          Instruction::Increment(Register::C)]);
-        assert_eq!(machine.value_of(Register::C), 1);
-        assert_eq!(machine.value_of(Register::D), 0);
+        machine.execute(&program).unwrap();
+        assert_eq!(machine.value_of(Register::A), 3);
+    }
+
+    #[test]
+    fn run_out() {
+        let program = parse_program("cpy 3 a\nout a\ndec a\njnz a -2").unwrap();
+        let mut machine = Machine::default();
+        machine.execute(&program).unwrap();
+        assert_eq!(machine.output(), &[3, 2, 1]);
+    }
+
+    #[test]
+    fn errors_have_line_numbers() {
+        assert_eq!(puzzle("inc a\nmul a b"),
+                   Err(MachineError::Parse {
+                       line: 2,
+                       message: "Unknown instruction: mul".to_string(),
+                   }));
+        assert_eq!(puzzle("inc a\ninc a\ncpy a 2"),
+                   Err(MachineError::InvalidOperand {
+                       line: 3,
+                       instruction: Instruction::Copy(Register::A.into(), FromLocation::Integer(2)),
+                   }));
+        assert_eq!(puzzle("inc a\njnz a -2"),
+                   Err(MachineError::JumpOutOfRange { line: 2, offset: -2 }));
+    }
+
+    #[test]
+    fn step_and_run_until() {
+        let mut machine = Machine::default();
+        machine.load(&parse_program("cpy 2 a\ninc b\ndec a\njnz a -2").unwrap()).unwrap();
+        assert_eq!(machine.step(), Ok(true));
+        assert_eq!(machine.step(), Ok(true));
+        assert_eq!(machine.registers(), [2, 1, 0, 0]);
+        assert_eq!(machine.prog_count(), 2);
+
+        assert_eq!(machine.run_until(|m| m.value_of(Register::B) == 2), Ok(true));
+        assert_eq!(machine.registers(), [1, 2, 0, 0]);
+        assert_eq!(machine.prog_count(), 2);
+
+        assert_eq!(machine.run_until(|_| false), Ok(false));
+        assert!(machine.is_halted());
+        assert_eq!(machine.step(), Ok(false));
+        assert_eq!(machine.registers(), [0, 2, 0, 0]);
+    }
+
+    #[test]
+    fn snapshot_and_restore() {
+        let mut machine = Machine::default();
+        machine.load(&parse_program("cpy 2 a\ntgl a\ninc b\ndec b\ninc c").unwrap()).unwrap();
+        machine.run_until(|m| m.prog_count() == 1).unwrap();
+        let snapshot = machine.snapshot();
+
+        machine.run_until(|_| false).unwrap();
+        assert_eq!(machine.registers(), [2, 2, 1, 0]);
+        assert!(machine.snapshot() != snapshot);
+
+        machine.restore(&snapshot);
+        assert_eq!(machine.snapshot(), snapshot);
+        assert_eq!(machine.registers(), [2, 0, 0, 0]);
+        machine.run_until(|_| false).unwrap();
+        assert_eq!(machine.registers(), [2, 2, 1, 0]);
     }
 }
@@ -8,6 +8,6 @@ fn main() {
     let mut input = String::new();
     file.read_to_string(&mut input).expect("Could not read input.txt");
 
-    let answer = twelve::puzzle(&input);
+    let answer = twelve::puzzle(&input).expect("Program failed");
     println!("The answer is {}", answer);
 }
@@ -0,0 +1,190 @@
+//! A peephole optimiser that replaces counting loops with arithmetic.
+//!
+//! Assembunny can only add and multiply by counting registers down in loops, so a program that
+//! multiplies two large numbers runs billions of instructions. [`optimise`] spots those loops and
+//! swaps the `jnz` that closes each one for the synthetic `AddLoop` or `MultiplyLoop`
+//! instruction, leaving the rest of the loop (and so every jump offset) as it was.
+//!
+//! The synthetic instructions only take the shortcut when the loop counter is positive, as it is
+//! in the puzzles. Otherwise they jump back just like the `jnz`, and the original loop runs.
+
+use super::{FromLocation, Instruction, Register};
+use Instruction::*;
+
+/// Return `program` with its add and multiply loops replaced.
+///
+/// The program is returned unchanged if it could modify itself (it contains a `tgl`), since
+/// then a loop could be rewritten after it had been replaced.
+pub fn optimise(program: &[Instruction]) -> Vec<Instruction> {
+    let mut optimised = program.to_vec();
+    if program.iter().any(|instruction| matches!(instruction, Toggle(_))) {
+        return optimised;
+    }
+    for end in 0..program.len() {
+        let code = &program[..=end];
+        if let Some(replacement) = multiply_loop(code).or_else(|| add_loop(code)) {
+            optimised[end] = replacement;
+        }
+    }
+    optimised
+}
+
+/// `inc t; dec c; jnz c -2` (in either order, or with `dec t`) adds (or subtracts) `c` to `t`.
+/// Looks for one at the end of `code`.
+fn add_loop(code: &[Instruction]) -> Option<Instruction> {
+    let (target, counter, delta) = add_loop_registers(code)?;
+    Some(AddLoop(counter, target, delta))
+}
+
+/// The target, counter and direction of an add loop at the end of `code`.
+fn add_loop_registers(code: &[Instruction]) -> Option<(Register, Register, i32)> {
+    let code = code.get(code.len().checked_sub(3)?..)?;
+    let counter = match code[2] {
+        JumpNonZero(FromLocation::Register(counter), FromLocation::Integer(-2)) => counter,
+        _ => return None,
+    };
+    let is_counter = |instruction: Instruction| instruction == Decrement(counter.into());
+    let body = if is_counter(code[1]) {
+        code[0]
+    } else if is_counter(code[0]) {
+        code[1]
+    } else {
+        return None;
+    };
+    match body {
+        Increment(FromLocation::Register(target)) if target != counter => {
+            Some((target, counter, 1))
+        }
+        Decrement(FromLocation::Register(target)) if target != counter => {
+            Some((target, counter, -1))
+        }
+        _ => None,
+    }
+}
+
+/// `cpy x c; inc t; dec c; jnz c -2; dec d; jnz d -5` adds `x * d` to `t`. Looks for one at the
+/// end of `code`.
+fn multiply_loop(code: &[Instruction]) -> Option<Instruction> {
+    let code = code.get(code.len().checked_sub(6)?..)?;
+    let (x, inner) = match code[0] {
+        Copy(x, FromLocation::Register(inner)) => (x, inner),
+        _ => return None,
+    };
+    let (target, counter, delta) = add_loop_registers(&code[1..4])?;
+    let outer = match (code[4], code[5]) {
+        (Decrement(FromLocation::Register(outer)),
+         JumpNonZero(FromLocation::Register(jump), FromLocation::Integer(-5))) if outer == jump => {
+            outer
+        }
+        _ => return None,
+    };
+    let distinct = counter == inner && outer != inner && outer != target;
+    let x_constant = x != inner.into() && x != target.into() && x != outer.into();
+    if delta < 0 || !distinct || !x_constant {
+        return None;
+    }
+    Some(MultiplyLoop(x, inner, outer, target))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use {parse_program, Machine, MachineError};
+
+    /// Run `source` with and without optimisation, checking both end in the same state.
+    fn run_both(source: &str) -> (Machine, Machine) {
+        let program = parse_program(source).unwrap();
+        let mut plain = Machine::default();
+        plain.execute(&program).unwrap();
+        let mut optimised = Machine::default();
+        optimised.execute(&optimise(&program)).unwrap();
+        assert_eq!(plain.registers, optimised.registers);
+        (plain, optimised)
+    }
+
+    #[test]
+    fn add_loops() {
+        let (plain, optimised) = run_both(include_str!("../input.txt"));
+        assert_eq!(optimised.value_of(Register::A), 9227731);
+        assert!(optimised.steps() * 1000 < plain.steps());
+
+        let (_, optimised) = run_both("cpy 10 a\ncpy 4 b\ndec b\ndec a\njnz b -2");
+        assert_eq!(optimised.value_of(Register::A), 6);
+    }
+
+    #[test]
+    fn multiply_loop() {
+        let source = "cpy 7 a
+                      cpy a b
+                      dec b
+                      cpy a d
+                      cpy 0 a
+                      cpy b c
+                      inc a
+                      dec c
+                      jnz c -2
+                      dec d
+                      jnz d -5";
+        let program = parse_program(source).unwrap();
+        let mut expected = program.clone();
+        expected[8] = AddLoop(Register::C, Register::A, 1);
+        expected[10] = MultiplyLoop(Register::B.into(), Register::C, Register::D, Register::A);
+        assert_eq!(optimise(&program), expected);
+        let (_, optimised) = run_both(source);
+        assert_eq!(optimised.value_of(Register::A), 42);
+    }
+
+    #[test]
+    fn self_modifying_code_is_left_alone() {
+        let program = parse_program("cpy 2 a\ntgl a\ninc b\ndec a\njnz a -2").unwrap();
+        assert_eq!(optimise(&program), program);
+    }
+
+    #[test]
+    fn loops_jumped_into() {
+        let source = "cpy 3 b
+                      jnz 1 3
+                      inc a
+                      dec b
+                      jnz b -2";
+        let (plain, _) = run_both(source);
+        assert_eq!(plain.value_of(Register::A), 3);
+
+        // Straight to the outer `dec d`, skipping the inner loop that would set `c` to 0.
+        let (_, optimised) = run_both("cpy 2 b
+                                       cpy 3 d
+                                       cpy 5 c
+                                       jnz 1 5
+                                       cpy b c
+                                       inc a
+                                       dec c
+                                       jnz c -2
+                                       dec d
+                                       jnz d -5");
+        assert_eq!(optimised.registers(), [4, 2, 0, 0]);
+    }
+
+    #[test]
+    fn counters_that_are_not_positive() {
+        // The plain loops never finish (until the counters overflow), and nor should the
+        // optimised ones.
+        for source in &["cpy -3 c\ninc a\ndec c\njnz c -2",
+                        "dec a\ndec c\njnz c -2",
+                        "cpy 0 b\ncpy 3 d\ncpy b c\ninc a\ndec c\njnz c -2\ndec d\njnz d -5"] {
+            let program = parse_program(source).unwrap();
+            let mut plain = Machine::with_budget(10_000);
+            let mut optimised = Machine::with_budget(10_000);
+            assert_eq!(plain.execute(&program),
+                       Err(MachineError::BudgetExhausted { steps: 10_000 }));
+            assert_eq!(optimised.execute(&optimise(&program)),
+                       Err(MachineError::BudgetExhausted { steps: 10_000 }));
+            assert_eq!(plain.registers, optimised.registers);
+        }
+
+        // The inner loops are still shortened, but the outer one has to be run in full.
+        let source = "cpy -1 d\ncpy 2 b\ncpy b c\ninc a\ndec c\njnz c -2\ndec d\njnz d -5";
+        let mut optimised = Machine::with_budget(10_000);
+        assert!(optimised.execute(&optimise(&parse_program(source).unwrap())).is_err());
+        assert!(optimised.value_of(Register::D) < -1000);
+    }
+}
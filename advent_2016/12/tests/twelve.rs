@@ -1,8 +1,8 @@
 extern crate twelve;
 use twelve::*;
 
-//! Tests in tests/ are used for integration testing, they can't see your private functions.
-//! Tests in lib.rs can, so they can be used for unit tests.
+// Tests in tests/ are used for integration testing, they can't see your private functions.
+// Tests in lib.rs can, so they can be used for unit tests.
 
 #[test]
 fn basic() {
@@ -17,29 +17,29 @@ fn instr_parse(s: &str) -> Instruction {
 #[test]
 fn parse_cpy_integer_to_register() {
     assert_eq!(instr_parse("cpy 41 a"),
-               Instruction::Copy(FromLocation::Integer(41), Register::A));
+               Instruction::Copy(FromLocation::Integer(41), Register::A.into()));
 }
 
 #[test]
 fn parse_cpy_register_to_register() {
     assert_eq!(instr_parse("cpy a c"),
-               Instruction::Copy(FromLocation::Register(Register::A), Register::C));
+               Instruction::Copy(FromLocation::Register(Register::A), Register::C.into()));
 }
 
 #[test]
 fn parse_inc_register() {
-    assert_eq!(instr_parse("inc c"), Instruction::Increment(Register::C));
+    assert_eq!(instr_parse("inc c"), Instruction::Increment(Register::C.into()));
 }
 
 #[test]
 fn parse_dec_register() {
-    assert_eq!(instr_parse("dec b"), Instruction::Decrement(Register::B));
+    assert_eq!(instr_parse("dec b"), Instruction::Decrement(Register::B.into()));
 }
 
 #[test]
 fn parse_jnz() {
     assert_eq!(instr_parse("jnz d 2"),
-               Instruction::JumpNonZero(FromLocation::Register(Register::D), 2));
+               Instruction::JumpNonZero(FromLocation::Register(Register::D), FromLocation::Integer(2)));
 }
 
 #[test]
@@ -50,5 +50,45 @@ fn run_their_example() {
                  dec a
                  jnz a 2
                  dec a";
-    assert_eq!(puzzle(input), 42);
+    assert_eq!(puzzle(input), Ok(42));
+}
+
+#[test]
+fn parse_tgl_out() {
+    assert_eq!(instr_parse("tgl c"),
+               Instruction::Toggle(FromLocation::Register(Register::C)));
+    assert_eq!(instr_parse("out 1"), Instruction::Out(FromLocation::Integer(1)));
+}
+
+#[test]
+fn run_real_input() {
+    assert_eq!(puzzle(include_str!("../input.txt")), Ok(9227731));
+}
+
+#[test]
+fn clock_signal_repeats() {
+    use std::collections::HashSet;
+
+    // Transmits 0, 1, 0, 1, ... forever.
+    let program = parse_program("cpy 0 a
+                                 out a
+                                 inc a
+                                 out a
+                                 dec a
+                                 jnz 1 -4")
+        .unwrap();
+    let mut machine = Machine::with_budget(1000);
+    machine.load(&program).unwrap();
+
+    // Stop after every output, until the machine is back in a state it has been in before.
+    let mut seen = HashSet::new();
+    loop {
+        let transmitted = machine.output().len();
+        assert_eq!(machine.run_until(|m| m.output().len() > transmitted), Ok(true));
+        if !seen.insert(machine.snapshot()) {
+            break;
+        }
+    }
+    assert_eq!(machine.output(), &[0, 1, 0]);
+    assert_eq!(seen.len(), 2);
 }
@@ -1,40 +1,85 @@
+//! Runs programs of register instructions like `b inc 5 if a > 1`.
+//!
+//! The input is parsed into [`Instruction`]s first, so a bad line is reported with its position
+//! instead of causing a panic halfway through the run. A single evaluation then answers both parts
+//! of the puzzle.
+
 use std::collections::HashMap;
 
-#[derive(PartialEq)]
-pub enum Part { One, Two, }
-
-/// Parses a list of assembly instructions and returns the largest value stored in a register.
-pub fn get_largest(s: &str, p: &Part) -> isize {
-    let mut registers: HashMap<String, isize> = HashMap::new();
-    let mut max_val = 0;
-    for line in s.lines() {
-        let words: Vec<&str> = line.split_whitespace().collect();
-        assert_eq!(words[3], "if");
-        let condition = { // If the condition doesn't match we shouldn't perform the operation.
-            let lhs: isize = words[4].parse::<isize>().ok().unwrap_or_else(|| *registers.entry(words[4].to_owned()).or_insert(0));
-            let rhs: isize = words[6].parse::<isize>().ok().unwrap_or_else(|| *registers.entry(words[6].to_owned()).or_insert(0));
-            match words[5] {
-                ">" => lhs > rhs,
-                "<" => lhs < rhs,
-                "==" => lhs == rhs,
-                ">=" => lhs >= rhs,
-                "<=" => lhs <= rhs,
-                "!=" => lhs != rhs,
-                x => panic!(format!("Unknown operator in position 5: {}", x)),
-            }
-        };
-        if condition {
-            let register = registers.entry(String::from(words[0])).or_insert(0);
-            let number: isize = words[2].parse().unwrap();
-            match words[1] {
-                "inc" => *register += number,
-                "dec" => *register -= number,
-                x => panic!(format!("Unknown operator in position 1: {}", x)),
+pub mod parser;
+pub mod registers;
+
+pub use parser::{parse, Comparison, Condition, Instruction, Op, Operand, ParseError};
+pub use registers::Registers;
+
+/// What one instruction did when it was evaluated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceStep {
+    /// Index of the instruction in the program.
+    pub instruction: usize,
+    /// Whether the condition held, so the target register was changed.
+    pub executed: bool,
+    /// The target register's value after the instruction.
+    pub value: isize,
+}
+
+/// The result of running a whole program.
+#[derive(Debug, Clone)]
+pub struct Evaluation {
+    pub registers: Registers<String>,
+    pub trace: Vec<TraceStep>,
+}
+
+impl Evaluation {
+    /// The largest value in any register at the end of the program (part one).
+    pub fn largest_final(&self) -> isize {
+        self.registers.largest().map_or(0, |(_, value)| value)
+    }
+
+    /// The largest value held in any register while the program ran (part two).
+    pub fn max_ever(&self) -> isize {
+        self.registers.max_ever()
+    }
+
+    /// The final value of every register mentioned by the program.
+    pub fn final_values(&self) -> HashMap<String, isize> {
+        self.registers.iter().map(|(k, v)| (k.clone(), v)).collect()
+    }
+}
+
+/// Run `program` once, recording every step.
+pub fn evaluate(program: &[Instruction]) -> Evaluation {
+    let mut registers = Registers::new();
+    let mut trace = Vec::with_capacity(program.len());
+    for (i, instruction) in program.iter().enumerate() {
+        let condition = &instruction.condition;
+        let lhs = operand_value(&mut registers, &condition.lhs);
+        let rhs = operand_value(&mut registers, &condition.rhs);
+        let executed = condition.comparison.holds(lhs, rhs);
+        let value = if executed {
+            let delta = match instruction.op {
+                Op::Inc => instruction.amount,
+                Op::Dec => -instruction.amount,
             };
-            max_val = std::cmp::max(*register, max_val);
-        }
+            registers.add(instruction.target.clone(), delta)
+        } else {
+            registers.get(instruction.target.clone())
+        };
+        trace.push(TraceStep { instruction: i, executed, value });
+    }
+    Evaluation { registers, trace }
+}
+
+/// Parse and run a program in one go.
+pub fn run(s: &str) -> Result<Evaluation, ParseError> {
+    parse(s).map(|program| evaluate(&program))
+}
+
+fn operand_value(registers: &mut Registers<String>, operand: &Operand) -> isize {
+    match *operand {
+        Operand::Number(n) => n,
+        Operand::Register(ref name) => registers.get(name.clone()),
     }
-    if *p == Part::One { *registers.iter().max_by_key(|&(_, val)| val).unwrap().1 } else { max_val }
 }
 
 #[test]
@@ -44,18 +89,22 @@ b inc 5 if a > 1
 a inc 1 if b < 5
 c dec -10 if a >= 1
 c inc -20 if c == 10";
-    assert_eq!(get_largest(input.trim(), &Part::One), 1);
-    assert_eq!(get_largest(input.trim(), &Part::Two), 10);
+    let evaluation = run(input.trim()).unwrap();
+    assert_eq!(evaluation.largest_final(), 1);
+    assert_eq!(evaluation.max_ever(), 10);
+    assert_eq!(evaluation.trace.iter().map(|step| (step.executed, step.value)).collect::<Vec<_>>(),
+               vec![(false, 0), (true, 1), (true, 10), (true, -10)]);
+    assert_eq!(evaluation.final_values()["c"], -10);
 }
 
 #[test]
 fn problem_1() {
     let input = include_str!("../input");
-    assert_eq!(get_largest(input.trim(), &Part::One), 3612);
+    assert_eq!(run(input.trim()).unwrap().largest_final(), 3612);
 }
 
 #[test]
 fn problem_2() {
     let input = include_str!("../input");
-    assert_eq!(get_largest(input.trim(), &Part::Two), 3818);
+    assert_eq!(run(input.trim()).unwrap().max_ever(), 3818);
 }
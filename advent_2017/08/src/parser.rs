@@ -0,0 +1,205 @@
+use std::error::Error;
+use std::fmt;
+
+/// One line of the program: `<target> <op> <amount> if <condition>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub target: String,
+    pub op: Op,
+    pub amount: isize,
+    pub condition: Condition,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Inc,
+    Dec,
+}
+
+/// `<lhs> <comparison> <rhs>`, where each side is a register or a number.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub lhs: Operand,
+    pub comparison: Comparison,
+    pub rhs: Operand,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Register(String),
+    Number(isize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Greater,
+    Less,
+    Equal,
+    GreaterOrEqual,
+    LessOrEqual,
+    NotEqual,
+}
+
+impl Comparison {
+    pub fn holds(self, lhs: isize, rhs: isize) -> bool {
+        match self {
+            Comparison::Greater => lhs > rhs,
+            Comparison::Less => lhs < rhs,
+            Comparison::Equal => lhs == rhs,
+            Comparison::GreaterOrEqual => lhs >= rhs,
+            Comparison::LessOrEqual => lhs <= rhs,
+            Comparison::NotEqual => lhs != rhs,
+        }
+    }
+}
+
+/// Where and why a line couldn't be parsed. Lines and columns start at 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for ParseError {
+    fn description(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Parse every non-blank line of `s` into an instruction.
+pub fn parse(s: &str) -> Result<Vec<Instruction>, ParseError> {
+    s.lines()
+        .enumerate()
+        .filter(|&(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| parse_line(line, i + 1))
+        .collect()
+}
+
+/// A word of the input and the column it starts at.
+type Token<'a> = (usize, &'a str);
+
+fn parse_line(line: &str, line_number: usize) -> Result<Instruction, ParseError> {
+    let mut tokens = tokenize(line).into_iter();
+    let end = line.chars().count() + 1;
+    let mut next = |expected: &str| {
+        tokens.next().ok_or_else(|| ParseError {
+            line: line_number,
+            column: end,
+            message: format!("expected {}, found end of line", expected),
+        })
+    };
+    let error = |(column, word): Token, expected: &str| ParseError {
+        line: line_number,
+        column,
+        message: format!("expected {}, found {:?}", expected, word),
+    };
+
+    let target = next("a register")?;
+    if !is_register(target.1) {
+        return Err(error(target, "a register"));
+    }
+    let op = match next("inc or dec")? {
+        (_, "inc") => Op::Inc,
+        (_, "dec") => Op::Dec,
+        token => return Err(error(token, "inc or dec")),
+    };
+    let amount = next("a number")?;
+    let amount = amount.1.parse().map_err(|_| error(amount, "a number"))?;
+    match next("if")? {
+        (_, "if") => {}
+        token => return Err(error(token, "if")),
+    }
+    let lhs = next("a register or number")?;
+    let lhs = operand(lhs).ok_or_else(|| error(lhs, "a register or number"))?;
+    let comparison = match next("a comparison")? {
+        (_, ">") => Comparison::Greater,
+        (_, "<") => Comparison::Less,
+        (_, "==") => Comparison::Equal,
+        (_, ">=") => Comparison::GreaterOrEqual,
+        (_, "<=") => Comparison::LessOrEqual,
+        (_, "!=") => Comparison::NotEqual,
+        token => return Err(error(token, "a comparison")),
+    };
+    let rhs = next("a register or number")?;
+    let rhs = operand(rhs).ok_or_else(|| error(rhs, "a register or number"))?;
+    if let Some(token) = tokens.next() {
+        return Err(error(token, "end of line"));
+    }
+
+    Ok(Instruction {
+        target: target.1.to_owned(),
+        op,
+        amount,
+        condition: Condition { lhs, comparison, rhs },
+    })
+}
+
+/// Split a line on whitespace, remembering the (1-based) column of each word.
+fn tokenize(line: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (column, (i, c)) in line.char_indices().enumerate() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some((column + 1, i)),
+            (true, Some((col, from))) => {
+                tokens.push((col, &line[from..i]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some((col, from)) = start {
+        tokens.push((col, &line[from..]));
+    }
+    tokens
+}
+
+fn is_register(word: &str) -> bool {
+    !word.is_empty() && word.chars().all(|c| c.is_alphanumeric() || c == '_') &&
+        !word.starts_with(|c: char| c.is_ascii_digit())
+}
+
+fn operand((_, word): Token) -> Option<Operand> {
+    if let Ok(n) = word.parse() {
+        Some(Operand::Number(n))
+    } else if is_register(word) {
+        Some(Operand::Register(word.to_owned()))
+    } else {
+        None
+    }
+}
+
+#[test]
+fn parse_instruction() {
+    assert_eq!(parse("c dec -10 if a >= 1"),
+               Ok(vec![Instruction {
+                   target: "c".to_owned(),
+                   op: Op::Dec,
+                   amount: -10,
+                   condition: Condition {
+                       lhs: Operand::Register("a".to_owned()),
+                       comparison: Comparison::GreaterOrEqual,
+                       rhs: Operand::Number(1),
+                   },
+               }]));
+}
+
+#[test]
+fn parse_errors() {
+    let error = |line, column, message: &str| {
+        Err(ParseError { line, column, message: message.to_owned() })
+    };
+    assert_eq!(parse("b inc 5 if a > 1\na  mul 1 if b < 5"),
+               error(2, 4, "expected inc or dec, found \"mul\""));
+    assert_eq!(parse("b inc five if a > 1"), error(1, 7, "expected a number, found \"five\""));
+    assert_eq!(parse("b inc 5 if a =< 1"), error(1, 14, "expected a comparison, found \"=<\""));
+    assert_eq!(parse("b inc 5 if a >"), error(1, 15, "expected a register or number, found end of line"));
+    assert_eq!(parse("b inc 5 if a > 1 else"), error(1, 18, "expected end of line, found \"else\""));
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A bank of named integer registers, all starting at zero.
+///
+/// Keeps track of the largest value any register has held, so callers don't have to rescan the
+/// registers after every write. Nothing here is specific to one puzzle's instruction set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Registers<K: Eq + Hash> {
+    values: HashMap<K, isize>,
+    max_ever: isize,
+}
+
+impl<K: Eq + Hash> Default for Registers<K> {
+    fn default() -> Self {
+        Registers { values: HashMap::new(), max_ever: 0 }
+    }
+}
+
+impl<K: Eq + Hash> Registers<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The value of a register, creating it (as zero) if it hasn't been used yet.
+    pub fn get(&mut self, register: K) -> isize {
+        *self.values.entry(register).or_insert(0)
+    }
+
+    /// Set a register, returning the new value.
+    pub fn set(&mut self, register: K, value: isize) -> isize {
+        self.values.insert(register, value);
+        self.max_ever = self.max_ever.max(value);
+        value
+    }
+
+    /// Add `delta` to a register, returning the new value.
+    pub fn add(&mut self, register: K, delta: isize) -> isize {
+        let value = {
+            let value = self.values.entry(register).or_insert(0);
+            *value += delta;
+            *value
+        };
+        self.max_ever = self.max_ever.max(value);
+        value
+    }
+
+    /// The register currently holding the largest value, if any register has been used.
+    pub fn largest(&self) -> Option<(&K, isize)> {
+        self.values.iter().map(|(k, &v)| (k, v)).max_by_key(|&(_, v)| v)
+    }
+
+    /// The largest value any register has held (registers start at zero, so at least zero).
+    pub fn max_ever(&self) -> isize {
+        self.max_ever
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, isize)> {
+        self.values.iter().map(|(k, &v)| (k, v))
+    }
+}
+
+#[test]
+fn tracks_max_ever() {
+    let mut registers = Registers::new();
+    assert_eq!(registers.largest(), None);
+    assert_eq!(registers.add("a", 5), 5);
+    assert_eq!(registers.add("a", -7), -2);
+    assert_eq!(registers.get("b"), 0);
+    assert_eq!(registers.set("c", 3), 3);
+    assert_eq!(registers.largest(), Some((&"c", 3)));
+    assert_eq!(registers.max_ever(), 5);
+}
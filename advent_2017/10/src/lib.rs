@@ -1,12 +1,50 @@
-#![feature(inclusive_range_syntax)]
+use std::hash::Hasher;
+
+/// The lengths appended to the input of the full Knot Hash.
+const SUFFIX: [u8; 5] = [17, 31, 73, 47, 23];
+
+/// The state of a list being knotted: the list, the current position, and the skip size.
+#[derive(Clone, Debug)]
+struct Knot {
+    list: Vec<usize>,
+    position: usize,
+    skip_size: usize,
+}
+
+impl Knot {
+    /// A list of the numbers `0..size`, in order.
+    fn new(size: usize) -> Self {
+        Knot { list: (0..size).collect(), position: 0, skip_size: 0 }
+    }
+
+    /// Do one round, tying a knot for each length. Lengths longer than the list are skipped
+    /// over without reversing anything.
+    fn round<I: IntoIterator<Item = usize>>(&mut self, lengths: I) {
+        let size = self.list.len();
+        for length in lengths {
+            if length <= size {
+                let (mut start, mut end) = (self.position, self.position + length);
+                while end > start + 1 {
+                    end -= 1;
+                    self.list.swap(start % size, end % size);
+                    start += 1;
+                }
+            }
+            self.position = (self.position + length + self.skip_size) % size;
+            self.skip_size += 1;
+        }
+    }
+
+    /// XOR together each block of 16 numbers (truncated to a byte).
+    fn dense(&self) -> Vec<u8> {
+        self.list.chunks(16).map(|chunk| chunk.iter().fold(0, |acc, &n| acc ^ n as u8)).collect()
+    }
+}
 
 /// Struct used in construction of the Knot Hash.
 pub struct KnotHash {
     lengths: Vec<u8>,
-    list: Vec<u8>,
-    list_max: u8,
-    position: u8,
-    skip_size: usize,
+    knot: Knot,
 }
 
 impl KnotHash {
@@ -14,30 +52,22 @@ impl KnotHash {
     /// and appending the magic vector `[17,31,73,47,23]`.
     pub fn from(lengths: &str, list_max: u8) -> Self {
         let mut new_lengths: Vec<_> = lengths.trim().as_bytes().to_vec();
-        new_lengths.append(&mut vec![17, 31, 73, 47, 23]);
+        new_lengths.extend_from_slice(&SUFFIX);
         KnotHash {
             lengths: new_lengths,
-            list: (0..=list_max).collect(),
-            list_max,
-            position: 0,
-            skip_size: 0,
+            knot: Knot::new(list_max as usize + 1),
         }
     }
 
     /// Returns a vector of bytes (`u8`) rather than the string representation.
     pub fn knot_hash_vec(&mut self) -> Vec<u8> {
         for _ in 0..64 { self.hash(); }
-        self.list.chunks(16).map(|chunk|
-            chunk.iter().fold(0, |acc, n| acc ^ n)
-        ).collect()
+        self.knot.dense()
     }
 
     /// Returns a String representing the Knot Hash in Hex.
     pub fn knot_hash(&mut self) -> String {
-        for _ in 0..64 { self.hash(); }
-        self.list.chunks(16).map(|chunk|
-            format!("{:02x}", chunk.iter().fold(0, |acc, n| acc ^ n))
-        ).collect()
+        self.knot_hash_vec().iter().map(|n| format!("{:02x}", n)).collect()
     }
 
     /// Converts a list of lengths to something that can be hashed.
@@ -45,35 +75,77 @@ impl KnotHash {
         KnotHash {
             lengths: lengths.trim().split(',')
                 .map(|length| length.parse::<u8>().expect(&format!("Couldn't parse {}", length))).collect(),
-            list: (0..=list_max).collect(),
-            list_max,
-            position: 0,
-            skip_size: 0,
+            knot: Knot::new(list_max as usize + 1),
         }
     }
 
     pub fn simple_hash(&mut self) -> usize {
         self.hash();
-        self.list[0] as usize * self.list[1] as usize
+        self.knot.list[0] * self.knot.list[1]
     }
 
     pub fn hash(&mut self) {
-        let list_len: usize = self.list_max as usize + 1;
-        for &length in &self.lengths {
-            if length > 1  && length <= self.list_max {
-                let mut start_pos = self.position as usize;
-                let mut end_pos = (self.position as usize + length as usize - 1) % list_len;
-                let mut swap_length = length;
-                while swap_length > 1 {
-                    self.list.swap(start_pos, end_pos);
-                    start_pos = (start_pos + 1) % list_len;
-                    end_pos = if end_pos == 0 { self.list_max as usize } else { end_pos - 1 };
-                    swap_length -= 2;
-                }
-            }
-            self.position = ((self.position as usize + self.skip_size as usize + length as usize) % list_len) as u8;
-            self.skip_size += 1;
+        self.knot.round(self.lengths.iter().map(|&length| length as usize));
+    }
+}
+
+/// A Knot Hash that is fed its input a piece at a time, knotting a list of `N` numbers (the
+/// real hash uses 256).
+///
+/// Every round of the hash goes over the whole input, so the input is kept until the hash is
+/// asked for. Cloning a hasher part way through gives a cheap way to hash several inputs that
+/// start the same way.
+#[derive(Clone, Debug, Default)]
+pub struct KnotHasher<const N: usize = 256> {
+    input: Vec<u8>,
+}
+
+impl<const N: usize> KnotHasher<N> {
+    pub fn new() -> Self {
+        KnotHasher { input: Vec::new() }
+    }
+
+    /// Add more bytes to the input.
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.input.extend_from_slice(bytes);
+    }
+
+    /// The list after all 64 rounds, before it's condensed.
+    pub fn sparse_hash(&self) -> Vec<usize> {
+        let mut knot = Knot::new(N);
+        let lengths = self.input.iter().chain(&SUFFIX).map(|&length| length as usize);
+        for _ in 0..64 {
+            knot.round(lengths.clone());
+        }
+        knot.list
+    }
+}
+
+impl KnotHasher {
+    /// The 16-byte hash of the input so far.
+    pub fn dense_hash(&self) -> [u8; 16] {
+        let mut hash = [0; 16];
+        for (byte, chunk) in hash.iter_mut().zip(self.sparse_hash().chunks(16)) {
+            *byte = chunk.iter().fold(0, |acc, &n| acc ^ n as u8);
         }
+        hash
+    }
+
+    /// The hash as a string of 32 hex digits.
+    pub fn hex(&self) -> String {
+        self.dense_hash().iter().map(|n| format!("{:02x}", n)).collect()
+    }
+}
+
+impl Hasher for KnotHasher {
+    /// The first eight bytes of the dense hash.
+    fn finish(&self) -> u64 {
+        let hash = self.dense_hash();
+        hash[..8].iter().fold(0, |acc, &byte| acc << 8 | byte as u64)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.update(bytes);
     }
 }
 
@@ -104,6 +176,32 @@ mod tests {
         assert_eq!(&KnotHash::from("1,2,4", 255).knot_hash(), "63960835bcdc130f0b66d7ff4f6a5a8e");
     }
 
+    #[test]
+    fn hasher() {
+        let mut hasher = KnotHasher::new();
+        hasher.update(b"AoC");
+        let prefix = hasher.clone();
+        hasher.write(b" 2017");
+        assert_eq!(hasher.hex(), "33efeb34ea91902bb2f59c9920caa6cd");
+        assert_eq!(hasher.dense_hash()[..4], [0x33, 0xef, 0xeb, 0x34]);
+        assert_eq!(hasher.finish(), 0x33efeb34ea91902b);
+        assert_eq!(prefix.hex(), KnotHash::from("AoC", 255).knot_hash());
+        assert_eq!(KnotHasher::<256>::new().hex(), "a2582a3a0e66e6e86e3812dcb672a272");
+    }
+
+    #[test]
+    fn list_sizes() {
+        let mut small = KnotHasher::<5>::new();
+        small.update(b"1,2,3");
+        assert_eq!(small.sparse_hash().len(), 5);
+
+        let mut large = KnotHasher::<1000>::new();
+        large.update(b"1,2,3");
+        let mut sparse = large.sparse_hash();
+        sparse.sort();
+        assert_eq!(sparse, (0..1000).collect::<Vec<_>>());
+    }
+
     #[test]
     fn problem_2() {
         let input = include_str!("../input");
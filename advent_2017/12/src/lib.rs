@@ -1,53 +1,82 @@
+extern crate union_find; // See ../../../union_find/ .
+
 use std::collections::HashMap;
-use std::collections::HashSet;
 
+use union_find::UnionFind;
+
+/// The programs in the village, grouped by which ones can talk to each other through pipes.
 pub struct Village {
-    pipes: HashMap<usize, Vec<usize>>,
+    groups: UnionFind,
+    /// Each program's element in `groups` (program numbers can have gaps, or be huge).
+    indices: HashMap<usize, usize>,
+    /// The program for each element of `groups`.
+    programs: Vec<usize>,
 }
 
 impl Village {
     pub fn from(pipe_list: &str) -> Self {
-        Village {
-            pipes: pipe_list.trim().lines().map(|line| {
-                let mut words = line.split_whitespace().map(|word| word.chars().filter(|&c| c != ',').collect::<String>()
-                                    .parse::<usize>().unwrap_or(0));
-                (words.next().unwrap(), words.skip(1).collect::<Vec<usize>>())
-            }).collect::<HashMap<usize, Vec<usize>>>(),
+        let mut village = Village { groups: UnionFind::new(0), indices: HashMap::new(), programs: Vec::new() };
+        for line in pipe_list.trim().lines() {
+            let mut words = line.split_whitespace().map(|word| word.chars().filter(|&c| c != ',').collect::<String>()
+                                .parse::<usize>().unwrap_or(0));
+            let program = words.next().unwrap();
+            village.add_program(program);
+            for other in words.skip(1) {
+                village.add_pipe(program, other);
+            }
         }
+        village
     }
 
-    pub fn take_group(&mut self, start: usize) -> HashSet<usize> {
-        let mut group: HashSet<usize> = HashSet::new();
-        let mut to_check: HashSet<usize> = HashSet::new();
-        to_check.insert(start);
-        while ! to_check.is_empty() {
-            let pipe = *to_check.iter().nth(0).unwrap();
-            if let Some(new_to_check) = self.pipes.get(&pipe).cloned() {
-                group.insert(pipe);
-                for p in new_to_check { to_check.insert(p); }
-                self.pipes.remove(&pipe);
-            }
-            to_check.remove(&pipe);
-        }
-        group
+    /// Connect two programs with a pipe, adding them to the village if they're new.
+    /// Returns whether this joined two groups that couldn't talk to each other before.
+    pub fn add_pipe(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.add_program(a), self.add_program(b));
+        self.groups.union(a, b)
     }
 
-    pub fn first_group(&mut self) -> usize {
-        self.take_group(0).len()
+    /// Add `program` if it's new, returning its element in `groups`.
+    fn add_program(&mut self, program: usize) -> usize {
+        let (groups, programs) = (&mut self.groups, &mut self.programs);
+        *self.indices.entry(program).or_insert_with(|| {
+            programs.push(program);
+            groups.add()
+        })
     }
 
-    pub fn count_groups(&mut self) -> usize {
-        let mut group_count = 0;
-        while ! self.pipes.is_empty() {
-            let first_pipe = *self.pipes.keys().nth(0).unwrap();
-            self.take_group(first_pipe);
-            group_count += 1;
+    /// Whether `program` is in the village.
+    pub fn contains(&self, program: usize) -> bool {
+        self.indices.contains_key(&program)
+    }
+
+    /// Whether two programs in the village can talk to each other.
+    pub fn connected(&mut self, a: usize, b: usize) -> bool {
+        match (self.indices.get(&a), self.indices.get(&b)) {
+            (Some(&a), Some(&b)) => self.groups.connected(a, b),
+            _ => false,
+        }
+    }
+
+    /// The programs in the same group as `program`, or none if it isn't in the village.
+    pub fn group(&self, program: usize) -> Vec<usize> {
+        match self.indices.get(&program) {
+            Some(&index) => self.groups.members(index).into_iter().map(|i| self.programs[i]).collect(),
+            None => Vec::new(),
         }
-        group_count
     }
-}
 
+    /// The size of the group containing program 0, or 0 if there's no program 0.
+    pub fn first_group(&mut self) -> usize {
+        match self.indices.get(&0) {
+            Some(&index) => self.groups.size(index),
+            None => 0,
+        }
+    }
 
+    pub fn count_groups(&self) -> usize {
+        self.groups.set_count()
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -69,6 +98,41 @@ mod tests {
         assert_eq!(Village::from(input).count_groups(), 2);
     }
 
+    #[test]
+    fn new_pipes() {
+        let mut village = Village::from("0 <-> 1\n1 <-> 0\n2 <-> 2\n");
+        assert!(!village.connected(0, 2));
+        assert!(village.add_pipe(2, 1));
+        assert!(village.connected(0, 2));
+        assert!(!village.add_pipe(0, 2));
+        assert!(!village.connected(0, 5));
+        village.add_pipe(5, 6);
+        assert_eq!(village.count_groups(), 2);
+        let mut group = village.group(1);
+        group.sort();
+        assert_eq!(group, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn missing_programs() {
+        let mut village = Village::from("1 <-> 3
+3 <-> 1
+");
+        assert_eq!(village.first_group(), 0);
+        assert_eq!(village.group(2), Vec::<usize>::new());
+        assert_eq!(village.group(9999), Vec::<usize>::new());
+        assert_eq!(village.group(3).len(), 2);
+        assert!(!village.connected(2, 2));
+        assert!(village.connected(3, 3));
+        assert_eq!(village.count_groups(), 1);
+        assert_eq!(Village::from("").first_group(), 0);
+
+        let mut village = Village::from("4000000000 <-> 1\n1 <-> 4000000000\n");
+        assert!(village.connected(1, 4_000_000_000));
+        assert_eq!(village.group(4_000_000_000).len(), 2);
+        assert_eq!(village.count_groups(), 1);
+    }
+
     #[test]
     fn problem_1() {
         assert_eq!(Village::from(include_str!("../input")).first_group(), 128);
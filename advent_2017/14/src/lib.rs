@@ -1,42 +1,43 @@
 extern crate ten; // See ../10/ .
 extern crate bit_vec;
+extern crate union_find; // See ../../../union_find/ .
 
-use ten::KnotHash;
+use ten::KnotHasher;
 use bit_vec::BitVec;
+use union_find::UnionFind;
+
+/// The knot hashes of the 128 rows of the grid for key `s`.
+fn row_hashes(s: &str) -> Vec<[u8; 16]> {
+    let mut key = KnotHasher::new();
+    key.update(s.as_bytes());
+    key.update(b"-");
+    (0..128).map(|n| {
+        let mut row = key.clone();
+        row.update(n.to_string().as_bytes());
+        row.dense_hash()
+    }).collect()
+}
 
 pub fn defrag(s: &str) -> u32 {
-    (0..128).fold(0, |count, n| {
-        KnotHash::from(&format!("{}-{}", s, n), 255).knot_hash_vec().iter()
-            .fold(0, |c, &h| c + h.count_ones()) + count
-    })
+    row_hashes(s).iter().flat_map(|row| row.iter()).map(|byte| byte.count_ones()).sum()
 }
 
+/// The number of groups of used squares that touch each other horizontally or vertically.
 pub fn regions(s: &str) -> u32 {
-    let mut grid = (0..128).map(|n| {
-        BitVec::from_bytes(&KnotHash::from(&format!("{}-{}", s, n), 255).knot_hash_vec())
-    }).collect::<Vec<_>>();
-    let mut regions = 0;
-    let mut to_check = Vec::new();
-    let mut y = 0;
-    while y < 128 {
-        let mut x = 0;
-        while x < 128 {
-            if grid[y][x] {
-                regions += 1;
-                to_check.push((y,x));
-                while let Some((y,x)) = to_check.pop() {
-                    grid[y].set(x, false);
-                    if y > 0   && grid[y-1][x] { to_check.push((y-1, x)); }
-                    if y < 127 && grid[y+1][x] { to_check.push((y+1, x)); }
-                    if x > 0   && grid[y][x-1] { to_check.push((y, x-1)); }
-                    if x < 127 && grid[y][x+1] { to_check.push((y, x+1)); }
-                }
-            }
-            x += 1;
+    let grid = row_hashes(s).iter().map(|row| BitVec::from_bytes(row)).collect::<Vec<_>>();
+    let mut squares = UnionFind::new(128 * 128);
+    let mut used = 0;
+    let mut joined = 0;
+    for y in 0..128 {
+        for x in 0..128 {
+            if !grid[y][x] { continue; }
+            used += 1;
+            if x < 127 && grid[y][x+1] && squares.union(y * 128 + x, y * 128 + x + 1) { joined += 1; }
+            if y < 127 && grid[y+1][x] && squares.union(y * 128 + x, (y + 1) * 128 + x) { joined += 1; }
         }
-        y += 1;
     }
-    regions
+    // Each join merges two regions into one.
+    used - joined
 }
 
 #[cfg(test)]
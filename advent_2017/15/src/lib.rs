@@ -1,23 +1,114 @@
-pub fn count_matches_1(mut a: usize, mut b: usize, iterations: usize) -> usize {
-    (0..iterations).filter(|_| {
-        a = a * 16_807 % 2_147_483_647;
-        b = b * 48_271 % 2_147_483_647;
-        a & 0xFFFF == b & 0xFFFF
-    }).count()
+use std::thread;
+
+/// The factor generator A multiplies by.
+pub const FACTOR_A: u64 = 16_807;
+/// The factor generator B multiplies by.
+pub const FACTOR_B: u64 = 48_271;
+/// The modulus both generators use (`2^31 - 1`).
+pub const MODULUS: u64 = 2_147_483_647;
+
+/// A generator whose values are each the previous value times `factor`, modulo `modulus`.
+/// A picky generator only hands out the values that are multiples of `multiple`.
+#[derive(Clone, Debug)]
+pub struct Generator {
+    value: u64,
+    factor: u64,
+    modulus: u64,
+    multiple: u64,
+}
+
+impl Generator {
+    /// Panics if `modulus` is zero.
+    pub fn new(start: u64, factor: u64, modulus: u64) -> Self {
+        assert!(modulus > 0, "A generator's modulus must be positive");
+        Generator { value: start % modulus, factor, modulus, multiple: 1 }
+    }
+
+    /// Only hand out values that are multiples of `multiple`. Panics if `multiple` is zero, as
+    /// the generator would never hand out anything.
+    pub fn picky(self, multiple: u64) -> Self {
+        assert!(multiple > 0, "A picky generator's multiple must be positive");
+        Generator { multiple, ..self }
+    }
+
+    pub fn is_picky(&self) -> bool {
+        self.multiple > 1
+    }
+
+    /// Move on `n` steps in O(log n) time, as if `n` values had been generated (including
+    /// values a picky generator would have passed over).
+    pub fn skip_ahead(&mut self, n: u64) {
+        self.value = mul_mod(self.value, pow_mod(self.factor, n, self.modulus), self.modulus);
+    }
 }
 
-pub fn count_matches_2(mut a: usize, mut b: usize, iterations: usize) -> usize {
-    let mut matched_a = Vec::new();
-    let mut matched_b = Vec::new();
+impl Iterator for Generator {
+    type Item = u64;
 
-    while std::cmp::min(matched_a.len(), matched_b.len()) < iterations {
-        a = a * 16_807 % 2_147_483_647;
-        b = b * 48_271 % 2_147_483_647;
-        if a % 4 == 0 { matched_a.push(a); }
-        if b % 8 == 0 { matched_b.push(b); }
+    /// The next value, or `None` if a picky generator will never find another one it likes.
+    fn next(&mut self) -> Option<u64> {
+        // There are only `modulus` different values, so after that many the values repeat.
+        for _ in 0..self.modulus {
+            self.value = mul_mod(self.value, self.factor, self.modulus);
+            if self.value.is_multiple_of(self.multiple) { return Some(self.value); }
+        }
+        None
     }
+}
+
+fn mul_mod(a: u64, b: u64, modulus: u64) -> u64 {
+    (a as u128 * b as u128 % modulus as u128) as u64
+}
+
+/// `base^exponent % modulus`, by repeated squaring.
+fn pow_mod(mut base: u64, mut exponent: u64, modulus: u64) -> u64 {
+    let mut result = 1 % modulus;
+    while exponent > 0 {
+        if exponent & 1 == 1 { result = mul_mod(result, base, modulus); }
+        base = mul_mod(base, base, modulus);
+        exponent >>= 1;
+    }
+    result
+}
+
+/// Count how many of the next `pairs` pairs of values have the same lowest 16 bits.
+pub fn judge<A, B>(a: A, b: B, pairs: u64) -> usize
+    where A: Iterator<Item = u64>, B: Iterator<Item = u64>
+{
+    a.zip(b).take(pairs as usize).filter(|&(a, b)| a & 0xFFFF == b & 0xFFFF).count()
+}
+
+/// The same as `judge`, with the pairs split between `threads` threads. Each thread skips its
+/// generators ahead to the start of its share.
+///
+/// Picky generators can't be split up, as there's no telling how many values they'll pass
+/// over on the way.
+pub fn judge_parallel(a: &Generator, b: &Generator, pairs: u64, threads: u64) -> usize {
+    assert!(!a.is_picky() && !b.is_picky(), "Picky generators can't skip ahead by pairs");
+    let threads = threads.max(1);
+    let share = pairs.div_ceil(threads);
+    let handles: Vec<_> = (0..threads).map(|i| {
+        let start = (i * share).min(pairs);
+        let (mut a, mut b) = (a.clone(), b.clone());
+        a.skip_ahead(start);
+        b.skip_ahead(start);
+        let count = share.min(pairs - start);
+        thread::spawn(move || judge(a, b, count))
+    }).collect();
+    handles.into_iter().map(|handle| handle.join().unwrap()).sum()
+}
 
-    matched_a.iter().zip(matched_b).filter(|&(a, b)| a & 0xFFFF == b & 0xFFFF).count()
+pub fn count_matches_1(a: usize, b: usize, iterations: usize) -> usize {
+    let threads = thread::available_parallelism().map(|n| n.get() as u64).unwrap_or(1);
+    judge_parallel(&Generator::new(a as u64, FACTOR_A, MODULUS),
+                   &Generator::new(b as u64, FACTOR_B, MODULUS),
+                   iterations as u64, threads)
+}
+
+pub fn count_matches_2(a: usize, b: usize, iterations: usize) -> usize {
+    judge(Generator::new(a as u64, FACTOR_A, MODULUS).picky(4),
+          Generator::new(b as u64, FACTOR_B, MODULUS).picky(8),
+          iterations as u64)
 }
 
 #[cfg(test)]
@@ -31,6 +122,57 @@ mod tests {
         assert_eq!(count_matches_2(65, 8921, 5_000_000), 309);
     }
 
+    #[test]
+    fn generators() {
+        let a = Generator::new(65, FACTOR_A, MODULUS);
+        assert_eq!(a.clone().take(5).collect::<Vec<_>>(),
+                   vec![1_092_455, 1_181_022_009, 245_556_042, 1_744_312_007, 1_352_636_452]);
+        assert_eq!(a.picky(4).take(3).collect::<Vec<_>>(),
+                   vec![1_352_636_452, 1_992_081_072, 530_830_436]);
+        assert_eq!(Generator::new(8921, FACTOR_B, MODULUS).picky(8).next(), Some(1_233_683_848));
+    }
+
+    #[test]
+    #[should_panic(expected = "modulus must be positive")]
+    fn zero_modulus() {
+        Generator::new(65, FACTOR_A, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "multiple must be positive")]
+    fn picky_about_zero() {
+        Generator::new(65, FACTOR_A, MODULUS).picky(0);
+    }
+
+    #[test]
+    fn picky_about_everything() {
+        // 3, 2, 6, 4, 5, 1, ... are never multiples of 8.
+        assert_eq!(Generator::new(1, 3, 7).picky(8).next(), None);
+        assert_eq!(Generator::new(1, 3, 7).picky(2).take(4).collect::<Vec<_>>(), vec![2, 6, 4, 2]);
+        assert_eq!(Generator::new(5, 0, 7).next(), Some(0));
+    }
+
+    #[test]
+    fn skip_ahead() {
+        let mut a = Generator::new(65, FACTOR_A, MODULUS);
+        let expected = a.clone().nth(1_000).unwrap();
+        a.skip_ahead(1_000);
+        assert_eq!(a.next(), Some(expected));
+
+        let mut small = Generator::new(3, 5, 7);
+        small.skip_ahead(u64::MAX);
+        assert!(small.value < 7);
+    }
+
+    #[test]
+    fn split_between_threads() {
+        let a = Generator::new(65, FACTOR_A, MODULUS);
+        let b = Generator::new(8921, FACTOR_B, MODULUS);
+        for &(pairs, threads) in &[(100_000, 1), (100_000, 3), (7, 16), (0, 4)] {
+            assert_eq!(judge_parallel(&a, &b, pairs, threads), judge(a.clone(), b.clone(), pairs));
+        }
+    }
+
     #[test]
     fn problem_1() {
         assert_eq!(count_matches_1(289, 629, 40_000_000), 638);
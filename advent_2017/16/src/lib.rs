@@ -9,7 +9,7 @@ pub enum Move {
 }
 
 impl FromStr for Move {
-    type Err = Box<Error>;
+    type Err = Box<dyn Error>;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Ok(match s.chars().nth(0).ok_or_else(|| "Missing move type".to_string())? {
             's' => Move::Spin(s.chars().skip(1).collect::<String>().parse::<usize>()?),
@@ -22,44 +22,96 @@ impl FromStr for Move {
     }
 }
 
-/// Returns the resulting program order after executing a series of dance moves (`moves`), on a
-/// set of programs from a->z (limited by `length`), a number of times (iterations).
-pub fn dance(moves: &str, length: usize, iterations: usize) -> String {
-    debug_assert!(length <= 26);
-    if length == 1 { return String::from("a") }
-    let mut programs = "abcdefghijklmnopqrstuvwxyz".chars().take(length).collect::<Vec<char>>();
-    let mut cycles = Vec::with_capacity(length);
-    cycles.push(programs.clone());
-    let moves: Vec<_> = moves.trim().split(',').map(|word| word.parse::<Move>().unwrap()).collect();
-    for _ in 0..iterations {
-        for m in &moves {
+/// A dance compiled into two permutations, which can be repeated any number of times cheaply.
+///
+/// Spins and exchanges move programs by position, whoever they are, while partner moves swap
+/// programs by name, wherever they are. The two kinds of move don't affect each other, so a
+/// dance is the same as doing all its positional moves and then renaming the programs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dance {
+    /// The position each program comes from: `new[i] = old[positions[i]]`.
+    positions: Vec<usize>,
+    /// The program each program is renamed to.
+    labels: Vec<usize>,
+}
+
+impl Dance {
+    /// Compile `moves` for `length` programs, named `a`, `b`, `c` and so on.
+    pub fn new(moves: &[Move], length: usize) -> Result<Self, Box<dyn Error>> {
+        let mut positions: Vec<usize> = (0..length).collect();
+        // Each program's current name, and which program has each name.
+        let mut labels: Vec<usize> = (0..length).collect();
+        let mut named: Vec<usize> = (0..length).collect();
+        for m in moves {
             match *m {
-                Move::Spin(n) => { if n == length || n == 0 { continue; }
-                    programs.reverse();
-                    let (a, b) = programs.split_at_mut(n);
-                    a.reverse(); b.reverse();
-                },
-                Move::Exchange(i,j) => {programs.swap(i,j);},
-                Move::Partner(a,b) => {
-                    let i = programs.iter().position(|&c| c == a).unwrap();
-                    let j = programs.iter().position(|&c| c == b).unwrap();
-                    programs.swap(i,j);
-                },
+                Move::Spin(n) => {
+                    if length > 0 { positions.rotate_right(n % length); }
+                }
+                Move::Exchange(i, j) => {
+                    if i >= length || j >= length {
+                        return Err(format!("Can't exchange {} and {} with {} programs", i, j, length).into());
+                    }
+                    positions.swap(i, j);
+                }
+                Move::Partner(a, b) => {
+                    let (a, b) = (program(a, length)?, program(b, length)?);
+                    labels.swap(named[a], named[b]);
+                    named.swap(a, b);
+                }
             }
         }
-        if cycles.iter().any(|cycle| cycle == &programs) {
-            break;
-        } else {
-            cycles.push(programs.clone());
-        }
+        Ok(Dance { positions, labels })
     }
-    let cycle_length = cycles.len();
-    if cycle_length < iterations {
-        cycles[iterations % cycle_length].clone().into_iter().collect()
-    } else {
-        programs.into_iter().collect()
+
+    /// This dance, done `n` times in a row.
+    pub fn repeat(&self, n: u64) -> Self {
+        Dance { positions: power(&self.positions, n), labels: power(&self.labels, n) }
+    }
+
+    /// The order of the programs after the dance, starting from alphabetical order.
+    pub fn order(&self) -> String {
+        self.positions.iter().map(|&i| name(self.labels[i])).collect()
+    }
+}
+
+/// Program `i`'s name: `a` for 0, `b` for 1 and so on, carrying on past `z` if needed.
+fn name(i: usize) -> char {
+    std::char::from_u32('a' as u32 + i as u32).unwrap_or('?')
+}
+
+/// The number of the program called `c`.
+fn program(c: char, length: usize) -> Result<usize, Box<dyn Error>> {
+    let i = (c as usize).wrapping_sub('a' as usize);
+    if i >= length {
+        return Err(format!("There's no program called {} among {} programs", c, length).into());
+    }
+    Ok(i)
+}
+
+/// Doing permutation `a` then `b` (in either order if they're powers of the same permutation).
+fn compose(a: &[usize], b: &[usize]) -> Vec<usize> {
+    b.iter().map(|&i| a[i]).collect()
+}
+
+/// Permutation `p` done `n` times, by repeated squaring.
+fn power(p: &[usize], mut n: u64) -> Vec<usize> {
+    let mut result: Vec<usize> = (0..p.len()).collect();
+    let mut square = p.to_vec();
+    while n > 0 {
+        if n & 1 == 1 { result = compose(&result, &square); }
+        square = compose(&square, &square);
+        n >>= 1;
     }
+    result
 }
+
+/// Returns the resulting program order after executing a series of dance moves (`moves`), on a
+/// set of `length` programs named from `a`, a number of times (`iterations`).
+pub fn dance(moves: &str, length: usize, iterations: u64) -> String {
+    let moves: Vec<_> = moves.trim().split(',').map(|word| word.parse::<Move>().unwrap()).collect();
+    Dance::new(&moves, length).unwrap().repeat(iterations).order()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -69,6 +121,49 @@ mod tests {
         assert_eq!(dance("s1,x3/4,pe/b", 5, 1), String::from("baedc"));
     }
 
+    /// Do every move in turn, `iterations` times.
+    fn replay(moves: &str, length: usize, iterations: usize) -> String {
+        let mut programs: Vec<char> = (0..length).map(name).collect();
+        for _ in 0..iterations {
+            for m in moves.split(',').map(|word| word.parse::<Move>().unwrap()) {
+                match m {
+                    Move::Spin(n) => programs.rotate_right(n % length),
+                    Move::Exchange(i, j) => programs.swap(i, j),
+                    Move::Partner(a, b) => {
+                        let i = programs.iter().position(|&c| c == a).unwrap();
+                        let j = programs.iter().position(|&c| c == b).unwrap();
+                        programs.swap(i, j);
+                    }
+                }
+            }
+        }
+        programs.into_iter().collect()
+    }
+
+    #[test]
+    fn repeats_match_replaying() {
+        let moves = "s1,x3/4,pe/b,x0/2,pa/c,s3,pd/e";
+        for iterations in 0..20 {
+            assert_eq!(dance(moves, 5, iterations as u64), replay(moves, 5, iterations));
+        }
+        assert_eq!(dance("s1,x3/4,pe/b", 5, 2), String::from("ceadb"));
+    }
+
+    #[test]
+    fn many_programs() {
+        let moves = "s7,x0/39,pa/z,x12/3,p{/b,s31,x5/6";
+        assert_eq!(dance(moves, 40, 13), replay(moves, 40, 13));
+        assert_eq!(dance(moves, 40, u64::MAX).chars().count(), 40);
+        assert_eq!(dance("pa/b", 1000, 0).chars().count(), 1000);
+    }
+
+    #[test]
+    fn invalid_moves() {
+        assert!(Dance::new(&[Move::Exchange(1, 5)], 5).is_err());
+        assert!(Dance::new(&[Move::Partner('a', 'f')], 5).is_err());
+        assert!(Dance::new(&[Move::Spin(12)], 5).is_ok());
+    }
+
     #[test]
     fn problem_1() {
         assert_eq!(dance(include_str!("../input"), 16, 1), String::from("doeaimlbnpjchfkg"));
@@ -1,14 +1,18 @@
+extern crate circular_list; // See ../../../circular_list/ .
+
+use circular_list::CircularList;
+
 /// Fill a circular buffer with `0..=max_len` values, stepping `step_size` forward each time.
 /// Returns the value after the last value inserted.
 pub fn fill_buffer_last(max_len: usize, step_size: usize) -> usize {
-    let mut buffer = Vec::with_capacity(max_len+1);
-    let mut index = 0;
-    buffer.push(0);
+    let mut buffer = CircularList::with_capacity(max_len+1);
+    buffer.insert_after(0);
     for i in 1..max_len+1 {
-        index = (index + step_size) % i + 1;
-        buffer.insert(index, i);
+        buffer.rotate(step_size as isize);
+        buffer.insert_after(i);
     }
-    buffer[(index + 1) % (max_len + 1)]
+    buffer.rotate(1);
+    *buffer.current().unwrap()
 }
 
 /// Fill a circular buffer with `0..=max_len` values, stepping `step_size` forward each time.
@@ -0,0 +1,208 @@
+//! Find pairs of IDs that differ in at most a few positions.
+//!
+//! Comparing every pair is quadratic, so IDs are bucketed instead. Each ID is cut into `k + 1`
+//! blocks; by the pigeonhole principle two IDs of the same length that differ in at most `k`
+//! positions must agree exactly on at least one block. Only IDs sharing a bucket are compared,
+//! and a pair is reported from the first block they agree on, so it's only reported once.
+
+use std::collections::HashMap;
+
+/// Two IDs that are close enough.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    /// The index of the first ID added.
+    pub first: usize,
+    /// The index of the second ID added (always greater than `first`).
+    pub second: usize,
+    /// The (character) positions at which the IDs differ.
+    pub positions: Vec<usize>,
+    /// The characters the IDs have in common, in order.
+    pub common: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct HammingIndex {
+    max_distance: usize,
+    ids: Vec<Vec<char>>,
+    /// Map from (ID length, block number, block contents) to the IDs with that block.
+    buckets: HashMap<(usize, usize, Vec<char>), Vec<usize>>,
+}
+
+impl HammingIndex {
+    /// An empty index for finding IDs at most `max_distance` characters apart.
+    pub fn new(max_distance: usize) -> Self {
+        HammingIndex {
+            max_distance,
+            ids: Vec::new(),
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Add an ID, returning its index.
+    pub fn insert(&mut self, id: &str) -> usize {
+        let index = self.ids.len();
+        let chars: Vec<char> = id.chars().collect();
+        for (block, range) in self.blocks(chars.len()).enumerate() {
+            self.buckets
+                .entry((chars.len(), block, chars[range].to_vec()))
+                .or_default()
+                .push(index);
+        }
+        self.ids.push(chars);
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// The ID with this index.
+    pub fn id(&self, index: usize) -> String {
+        self.ids[index].iter().collect()
+    }
+
+    /// Every pair of IDs that are the same length and differ in at most `max_distance`
+    /// positions, ordered by `first` then `second`.
+    pub fn pairs(&self) -> Vec<Match> {
+        let mut matches = Vec::new();
+        for (&(len, block, _), bucket) in &self.buckets {
+            for (i, &first) in bucket.iter().enumerate() {
+                for &second in &bucket[i + 1..] {
+                    let (a, b) = (&self.ids[first], &self.ids[second]);
+                    let agree_earlier = self
+                        .blocks(len)
+                        .take(block)
+                        .any(|range| a[range.clone()] == b[range]);
+                    if agree_earlier {
+                        continue;
+                    }
+                    if let Some(m) = self.compare(first, second) {
+                        matches.push(m);
+                    }
+                }
+            }
+        }
+        matches.sort_by_key(|m| (m.first, m.second));
+        matches
+    }
+
+    /// Compare two IDs of the same length, giving up once they're too far apart.
+    fn compare(&self, first: usize, second: usize) -> Option<Match> {
+        let (a, b) = (&self.ids[first], &self.ids[second]);
+        let mut positions = Vec::new();
+        let mut common = String::with_capacity(a.len());
+        for (i, (x, y)) in a.iter().zip(b).enumerate() {
+            if x == y {
+                common.push(*x);
+            } else {
+                positions.push(i);
+                if positions.len() > self.max_distance {
+                    return None;
+                }
+            }
+        }
+        Some(Match {
+            first,
+            second,
+            positions,
+            common,
+        })
+    }
+
+    /// The ranges of the `max_distance + 1` blocks an ID of length `len` is cut into. Some
+    /// blocks are empty if the ID is shorter than that.
+    fn blocks(&self, len: usize) -> impl Iterator<Item = std::ops::Range<usize>> {
+        let count = self.max_distance + 1;
+        (0..count).map(move |block| block * len / count..(block + 1) * len / count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(max_distance: usize, ids: &[&str]) -> HammingIndex {
+        let mut index = HammingIndex::new(max_distance);
+        for id in ids {
+            index.insert(id);
+        }
+        index
+    }
+
+    #[test]
+    fn one_apart() {
+        let index = index(
+            1,
+            &[
+                "abcde", "fghij", "klmno", "pqrst", "fguij", "axcye", "wvxyz",
+            ],
+        );
+        assert_eq!(
+            index.pairs(),
+            vec![Match {
+                first: 1,
+                second: 4,
+                positions: vec![2],
+                common: String::from("fgij"),
+            }]
+        );
+        assert_eq!(index.id(4), "fguij");
+    }
+
+    #[test]
+    fn further_apart() {
+        let ids = [
+            "abcde", "axcye", "abcde", "xbcdy", "zzzzz", "abc", "abd", "ab",
+        ];
+        let pairs: Vec<_> = index(2, &ids)
+            .pairs()
+            .into_iter()
+            .map(|m| (m.first, m.second, m.positions))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                (0, 1, vec![1, 3]),
+                (0, 2, vec![]),
+                (0, 3, vec![0, 4]),
+                (1, 2, vec![1, 3]),
+                (2, 3, vec![0, 4]),
+                (5, 6, vec![2]),
+            ]
+        );
+        // IDs shorter than the distance are all close to each other.
+        assert_eq!(index(3, &["ab", "cd", "abc"]).pairs().len(), 1);
+    }
+
+    #[test]
+    fn many_ids() {
+        // Pseudo-random IDs, which are very unlikely to be close to each other by chance.
+        let mut state: u64 = 1;
+        let mut index = HammingIndex::new(2);
+        for _ in 0..200_000 {
+            let id: String = (0..26)
+                .map(|_| {
+                    state = state
+                        .wrapping_mul(6_364_136_223_846_793_005)
+                        .wrapping_add(1);
+                    (b'a' + (state >> 33) as u8 % 26) as char
+                })
+                .collect();
+            index.insert(&id);
+        }
+        let mut close = index.id(1234);
+        close.replace_range(3..4, "#");
+        close.replace_range(20..21, "#");
+        let added = index.insert(&close);
+
+        let pairs = index.pairs();
+        assert_eq!(pairs.len(), 1);
+        assert_eq!((pairs[0].first, pairs[0].second), (1234, added));
+        assert_eq!(pairs[0].positions, vec![3, 20]);
+        assert_eq!(pairs[0].common.len(), 24);
+    }
+}
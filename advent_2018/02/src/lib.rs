@@ -1,8 +1,12 @@
 #![feature(external_doc)]
 #![doc(include = "../Question.md")]
 
+pub mod hamming;
+
 use std::collections::HashMap;
 
+use crate::hamming::HammingIndex;
+
 const INPUT: &str = include_str!("../input");
 
 pub fn answer() -> (String, String) {
@@ -39,27 +43,18 @@ fn counts_map(id: &str) -> HashMap<char, u32> {
     counts_map
 }
 
+/// Takes a newline-separated list of IDs, and returns the letters in common between the first
+/// pair of IDs that differ by exactly one character.
 fn common_letters(ids: &str) -> Option<String> {
-    let ids: Vec<&str> = ids.lines().collect();
-
-    for (i, id) in ids.iter().enumerate() {
-        for other in ids.iter().skip(i + 1) {
-            let id_len = id.len();
-            if id_len != other.len() {
-                continue;
-            }
-            let common: String = id
-                .chars()
-                .zip(other.chars())
-                .filter(|(a, b)| a == b)
-                .map(|x| x.0)
-                .collect();
-            if common.len() == id_len - 1 {
-                return Some(common);
-            }
-        }
+    let mut index = HammingIndex::new(1);
+    for id in ids.lines() {
+        index.insert(id);
     }
-    None
+    index
+        .pairs()
+        .into_iter()
+        .find(|m| m.positions.len() == 1)
+        .map(|m| m.common)
 }
 
 #[cfg(test)]
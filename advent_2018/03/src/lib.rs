@@ -1,9 +1,9 @@
 #![feature(external_doc)]
 #![doc(include = "../Question.md")]
 
-use std::collections::{hash_map::Entry, HashMap, HashSet};
+use std::collections::HashSet;
 
-use failure::{bail, ensure, format_err, Error};
+use failure::{bail, format_err, Error};
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::str::FromStr;
@@ -12,10 +12,9 @@ const INPUT: &str = include_str!("../input");
 
 pub fn answer() -> (String, String) {
     let cloth = Cloth::from(INPUT).unwrap();
-    (
-        cloth.overlaps().unwrap().to_string(),
-        cloth.no_overlap().unwrap().to_string(),
-    )
+    let intact = cloth.no_overlap();
+    assert_eq!(intact.len(), 1, "Expected one claim without overlaps");
+    (cloth.overlaps().to_string(), intact[0].to_string())
 }
 
 #[derive(Debug, PartialEq)]
@@ -73,11 +72,25 @@ impl FromStr for Claim {
     }
 }
 
+/// A set of claims, and where they overlap.
+///
+/// Works on the claims' edges rather than on every square inch, so the size of the cloth
+/// doesn't matter, only the number of claims.
 #[derive(Debug)]
 struct Cloth {
-    claim_map: HashMap<(u32, u32), u32>,
-    overlapping: HashSet<u32>,
-    all: HashSet<u32>,
+    claims: Vec<Claim>,
+    /// Pairs of ids of claims that overlap, smaller id first, in order.
+    conflicts: Vec<(u32, u32)>,
+}
+
+impl Claim {
+    /// Whether two claims share at least one square inch.
+    fn overlaps(&self, other: &Claim) -> bool {
+        self.top_left.x < other.bottom_right.x
+            && other.top_left.x < self.bottom_right.x
+            && self.top_left.y < other.bottom_right.y
+            && other.top_left.y < self.bottom_right.y
+    }
 }
 
 impl Cloth {
@@ -95,49 +108,94 @@ impl Cloth {
                     .collect::<Vec<_>>()
             );
         }
-        let claims: Vec<_> = results.into_iter().map(Result::unwrap).collect();
-
-        let mut claim_map = HashMap::new();
-        let mut claim_ids = HashMap::new();
-        let mut overlapping = HashSet::new();
-        let mut all = HashSet::new();
-
-        for claim in claims {
-            all.insert(claim.id);
-            for x in claim.top_left.x..claim.bottom_right.x {
-                for y in claim.top_left.y..claim.bottom_right.y {
-                    *claim_map.entry((x, y)).or_insert(0) += 1;
-                    if let Entry::Vacant(entry) = claim_ids.entry((x, y)) {
-                        entry.insert(claim.id);
-                    } else {
-                        overlapping.insert(claim_ids[&(x, y)]);
-                        overlapping.insert(claim.id);
-                    }
+        let mut claims: Vec<_> = results.into_iter().map(Result::unwrap).collect();
+        claims.sort_by_key(|claim| claim.top_left.x);
+
+        // Sweep from left to right, comparing each claim with the ones that haven't ended yet.
+        let mut conflicts = Vec::new();
+        let mut active: Vec<&Claim> = Vec::new();
+        for claim in &claims {
+            active.retain(|other| other.bottom_right.x > claim.top_left.x);
+            for other in &active {
+                if claim.overlaps(other) {
+                    conflicts.push((claim.id.min(other.id), claim.id.max(other.id)));
                 }
             }
+            active.push(claim);
         }
-        Ok(Cloth {
-            claim_map,
-            overlapping,
-            all,
-        })
+        conflicts.sort();
+
+        Ok(Cloth { claims, conflicts })
     }
 
     /// Given an input that is a newline-separated list of claims, work out how many inches of
     /// the cloth have at least two overlapping claims.
-    fn overlaps(&self) -> Result<usize, Error> {
-        Ok(self.claim_map.values().filter(|v| **v > 1).count())
+    ///
+    /// Sweeps a vertical line across the cloth, stopping at each claim's left and right edges.
+    /// The line is split into segments at every claim's top and bottom edge, and each segment
+    /// keeps count of how many claims currently cover it.
+    fn overlaps(&self) -> u64 {
+        let mut ys: Vec<u32> = self
+            .claims
+            .iter()
+            .flat_map(|claim| vec![claim.top_left.y, claim.bottom_right.y])
+            .collect();
+        ys.sort();
+        ys.dedup();
+        let segment = |y: u32| ys.binary_search(&y).unwrap();
+
+        // (x, change in coverage, claim) for each left and right edge.
+        let mut edges: Vec<(u32, i32, &Claim)> = self
+            .claims
+            .iter()
+            .flat_map(|claim| {
+                vec![
+                    (claim.top_left.x, 1, claim),
+                    (claim.bottom_right.x, -1, claim),
+                ]
+            })
+            .collect();
+        edges.sort_by_key(|&(x, change, _)| (x, change));
+
+        let mut coverage = vec![0; ys.len()];
+        let mut area = 0;
+        let mut last_x = 0;
+        for (x, change, claim) in edges {
+            let overlapped: u64 = coverage
+                .iter()
+                .enumerate()
+                .filter(|&(_, &count)| count > 1)
+                .map(|(i, _)| u64::from(ys[i + 1] - ys[i]))
+                .sum();
+            area += overlapped * u64::from(x - last_x);
+            last_x = x;
+            for count in &mut coverage[segment(claim.top_left.y)..segment(claim.bottom_right.y)] {
+                *count += change;
+            }
+        }
+        area
     }
 
-    /// Return the ID of the claim with no overlap with any others.
-    fn no_overlap(&self) -> Result<u32, Error> {
-        let non_overlapping: HashSet<_> = self.all.difference(&self.overlapping).collect();
-        ensure!(
-            non_overlapping.len() == 1,
-            "Too many non-overlapping values found: {:?}",
-            non_overlapping
-        );
-        Ok(**non_overlapping.iter().next().unwrap())
+    /// Return the IDs of the claims with no overlap with any others, in order.
+    fn no_overlap(&self) -> Vec<u32> {
+        let overlapping: HashSet<u32> = self
+            .conflicts()
+            .iter()
+            .flat_map(|&(a, b)| vec![a, b])
+            .collect();
+        let mut ids: Vec<u32> = self
+            .claims
+            .iter()
+            .map(|claim| claim.id)
+            .filter(|id| !overlapping.contains(id))
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Every pair of claim IDs that overlap, smaller ID first.
+    fn conflicts(&self) -> &[(u32, u32)] {
+        &self.conflicts
     }
 }
 
@@ -162,7 +220,7 @@ mod tests {
         let input = "#1 @ 1,3: 4x4\n\
                      #2 @ 3,1: 4x4\n\
                      #3 @ 5,5: 2x2\n";
-        assert_eq!(Cloth::from(input).unwrap().overlaps().unwrap(), 4);
+        assert_eq!(Cloth::from(input).unwrap().overlaps(), 4);
     }
 
     #[test]
@@ -175,6 +233,23 @@ mod tests {
         let input = "#1 @ 1,3: 4x4\n\
                      #2 @ 3,1: 4x4\n\
                      #3 @ 5,5: 2x2\n";
-        assert_eq!(Cloth::from(input).unwrap().no_overlap().unwrap(), 3);
+        let cloth = Cloth::from(input).unwrap();
+        assert_eq!(cloth.no_overlap(), vec![3]);
+        assert_eq!(cloth.conflicts(), &[(1, 2)]);
+    }
+
+    #[test]
+    fn large_coordinates() {
+        let input = "#1 @ 0,0: 3000000x2000000\n\
+                     #2 @ 1000000,1000000: 3000000x3000000\n\
+                     #3 @ 3500000,3500000: 10x10\n\
+                     #4 @ 1500000,1500000: 100x100\n\
+                     #5 @ 3000000,0: 10x10\n";
+        let cloth = Cloth::from(input).unwrap();
+        // Claims 1 and 2 overlap in a 2000000x1000000 rectangle, which contains claim 4. Claim 3
+        // is inside claim 2, and claim 5 only touches claim 1's edge.
+        assert_eq!(cloth.overlaps(), 2_000_000 * 1_000_000 + 100);
+        assert_eq!(cloth.conflicts(), &[(1, 2), (1, 4), (2, 3), (2, 4)]);
+        assert_eq!(cloth.no_overlap(), vec![5]);
     }
 }
@@ -1,12 +1,13 @@
 #![feature(external_doc)]
 #![doc(include = "../Question.md")]
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
 use std::str::FromStr;
 
 // use chrono::prelude::*;
-use chrono::{offset::TimeZone, DateTime, Duration, Timelike, Utc};
-use failure::{bail, format_err, Error};
+use chrono::{offset::TimeZone, DateTime, Duration, NaiveDate, Timelike, Utc};
+use failure::{bail, ensure, format_err, Error};
 use lazy_static::lazy_static;
 use regex::Regex;
 
@@ -20,20 +21,32 @@ pub fn answer() -> (String, String) {
     )
 }
 
+/// The guards' records, in time order.
 #[derive(Debug, PartialEq)]
-struct Schedule {
+pub struct Schedule {
     events: Vec<Event>,
+    shifts: Vec<Shift>,
 }
 
 #[derive(Debug, PartialEq)]
-struct Event {
-    id: Option<u32>,
-    action: Action,
-    date_time: DateTime<Utc>,
+pub struct Event {
+    /// The guard on duty (`None` only if the records start before any guard begins a shift).
+    pub id: Option<u32>,
+    pub action: Action,
+    pub date_time: DateTime<Utc>,
+}
+
+/// One guard's shift: when it began and when they were asleep.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shift {
+    pub guard: u32,
+    pub begin: DateTime<Utc>,
+    /// Each nap as (fell asleep, woke up). The guard is awake again in the minute they wake up.
+    pub naps: Vec<(DateTime<Utc>, DateTime<Utc>)>,
 }
 
 #[derive(Debug, PartialEq)]
-enum Action {
+pub enum Action {
     BeginShift,
     Wake,
     Sleep,
@@ -46,7 +59,7 @@ struct SleepData {
 }
 
 impl Schedule {
-    fn from(s: &str) -> Result<Self, Error> {
+    pub fn from(s: &str) -> Result<Self, Error> {
         let mut events = s.lines().collect::<Vec<_>>();
         events.sort();
         let (results, errors): (Vec<_>, Vec<_>) = events
@@ -72,7 +85,72 @@ impl Schedule {
                 event.id = last_id;
             }
         }
-        Ok(Schedule { events })
+        let shifts = shifts(&events)?;
+        Ok(Schedule { events, shifts })
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Every shift, in the order they began.
+    pub fn shifts(&self) -> &[Shift] {
+        &self.shifts
+    }
+
+    /// The shifts that began before midnight.
+    pub fn shifts_spanning_midnight(&self) -> impl Iterator<Item = &Shift> {
+        self.shifts.iter().filter(|shift| shift.spans_midnight())
+    }
+
+    /// The ids of the guards that worked a shift, in order.
+    pub fn guards(&self) -> Vec<u32> {
+        let mut guards: Vec<u32> = self.shifts.iter().map(|shift| shift.guard).collect();
+        guards.sort();
+        guards.dedup();
+        guards
+    }
+
+    /// How many minutes each guard slept on each night, as (night, guard, minutes asleep) in
+    /// order.
+    pub fn minutes_asleep_by_night(&self) -> Vec<(NaiveDate, u32, i64)> {
+        let mut totals = BTreeMap::new();
+        for shift in &self.shifts {
+            *totals.entry((shift.night(), shift.guard)).or_insert(0) += shift.minutes_asleep();
+        }
+        totals
+            .into_iter()
+            .map(|((night, guard), minutes)| (night, guard, minutes))
+            .collect()
+    }
+
+    /// How many times `guard` was asleep in each minute past the hour.
+    pub fn histogram(&self, guard: u32) -> [u32; 60] {
+        let mut histogram = [0; 60];
+        for shift in self.shifts.iter().filter(|shift| shift.guard == guard) {
+            for minute in shift.asleep_minutes() {
+                histogram[minute as usize] += 1;
+            }
+        }
+        histogram
+    }
+
+    /// The guard × minute matrix of `histogram`s as CSV, with a header row of minutes and one
+    /// row per guard.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("guard");
+        for minute in 0..60 {
+            write!(csv, ",{}", minute).unwrap();
+        }
+        csv.push('\n');
+        for guard in self.guards() {
+            write!(csv, "{}", guard).unwrap();
+            for count in self.histogram(guard).iter() {
+                write!(csv, ",{}", count).unwrap();
+            }
+            csv.push('\n');
+        }
+        csv
     }
 
     fn sleep_data(&self) -> SleepData {
@@ -110,6 +188,74 @@ impl Schedule {
     }
 }
 
+/// Group sorted events into shifts, checking that guards only sleep during a shift and only wake
+/// up after falling asleep.
+fn shifts(events: &[Event]) -> Result<Vec<Shift>, Error> {
+    let mut shifts: Vec<Shift> = Vec::new();
+    let mut asleep = None;
+    for event in events {
+        if let Action::BeginShift = event.action {
+            ensure!(
+                asleep.is_none(),
+                "Shift began while a guard was asleep: {:?}",
+                event
+            );
+            shifts.push(Shift {
+                guard: event.id.unwrap(),
+                begin: event.date_time,
+                naps: Vec::new(),
+            });
+            continue;
+        }
+        let shift = shifts
+            .last_mut()
+            .ok_or_else(|| format_err!("Event before any shift began: {:?}", event))?;
+        match (&event.action, asleep) {
+            (Action::Sleep, None) => asleep = Some(event.date_time),
+            (Action::Wake, Some(fell_asleep)) => {
+                shift.naps.push((fell_asleep, event.date_time));
+                asleep = None;
+            }
+            _ => bail!("Guard #{} can't do that now: {:?}", shift.guard, event),
+        }
+    }
+    ensure!(asleep.is_none(), "The last guard never woke up");
+    Ok(shifts)
+}
+
+impl Shift {
+    /// The date of the midnight hour the shift covers (shifts can begin shortly before
+    /// midnight).
+    pub fn night(&self) -> NaiveDate {
+        let date = self.begin.naive_utc().date();
+        if self.begin.hour() >= 12 {
+            date + Duration::days(1)
+        } else {
+            date
+        }
+    }
+
+    /// Whether the shift began the evening before its night.
+    pub fn spans_midnight(&self) -> bool {
+        self.begin.naive_utc().date() != self.night()
+    }
+
+    pub fn minutes_asleep(&self) -> i64 {
+        self.naps
+            .iter()
+            .map(|(asleep, awake)| awake.signed_duration_since(*asleep).num_minutes())
+            .sum()
+    }
+
+    /// The minute past the hour of every minute the guard was asleep.
+    pub fn asleep_minutes(&self) -> impl Iterator<Item = u32> + '_ {
+        self.naps.iter().flat_map(|&(asleep, awake)| {
+            let minutes = awake.signed_duration_since(asleep).num_minutes();
+            (0..minutes).map(move |m| (asleep + Duration::minutes(m)).minute())
+        })
+    }
+}
+
 impl SleepData {
     fn most_asleep_by_guard(&self) -> u32 {
         let most_asleep_guard = &self.sleep_count.iter().max_by_key(|x| x.1).unwrap().0;
@@ -202,35 +348,35 @@ impl FromStr for Action {
 mod tests {
     use super::*;
 
+    const EXAMPLE: &str = "[1518-11-01 00:25] wakes up\n\
+                           [1518-11-04 00:46] wakes up\n\
+                           [1518-11-01 00:30] falls asleep\n\
+                           [1518-11-01 00:55] wakes up\n\
+                           [1518-11-01 23:58] Guard #99 begins shift\n\
+                           [1518-11-02 00:40] falls asleep\n\
+                           [1518-11-05 00:45] falls asleep\n\
+                           [1518-11-02 00:50] wakes up\n\
+                           [1518-11-01 00:05] falls asleep\n\
+                           [1518-11-03 00:05] Guard #10 begins shift\n\
+                           [1518-11-03 00:29] wakes up\n\
+                           [1518-11-04 00:02] Guard #99 begins shift\n\
+                           [1518-11-04 00:36] falls asleep\n\
+                           [1518-11-05 00:03] Guard #99 begins shift\n\
+                           [1518-11-03 00:24] falls asleep\n\
+                           [1518-11-05 00:55] wakes up\n\
+                           [1518-11-01 00:00] Guard #10 begins shift\n";
+
     #[test]
     fn examples() {
-        let input = "[1518-11-01 00:25] wakes up\n\
-                     [1518-11-04 00:46] wakes up\n\
-                     [1518-11-01 00:30] falls asleep\n\
-                     [1518-11-01 00:55] wakes up\n\
-                     [1518-11-01 23:58] Guard #99 begins shift\n\
-                     [1518-11-02 00:40] falls asleep\n\
-                     [1518-11-05 00:45] falls asleep\n\
-                     [1518-11-02 00:50] wakes up\n\
-                     [1518-11-01 00:05] falls asleep\n\
-                     [1518-11-03 00:05] Guard #10 begins shift\n\
-                     [1518-11-03 00:29] wakes up\n\
-                     [1518-11-04 00:02] Guard #99 begins shift\n\
-                     [1518-11-04 00:36] falls asleep\n\
-                     [1518-11-05 00:03] Guard #99 begins shift\n\
-                     [1518-11-03 00:24] falls asleep\n\
-                     [1518-11-05 00:55] wakes up\n\
-                     [1518-11-01 00:00] Guard #10 begins shift\n";
-
         assert_eq!(
-            Schedule::from(input)
+            Schedule::from(EXAMPLE)
                 .unwrap()
                 .sleep_data()
                 .most_asleep_by_guard(),
             240
         );
         assert_eq!(
-            Schedule::from(input)
+            Schedule::from(EXAMPLE)
                 .unwrap()
                 .sleep_data()
                 .most_asleep_by_minute(),
@@ -238,6 +384,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn queries() {
+        let schedule = Schedule::from(EXAMPLE).unwrap();
+        assert_eq!(schedule.events().len(), 17);
+        assert_eq!(schedule.guards(), vec![10, 99]);
+        assert_eq!(schedule.shifts().len(), 5);
+
+        let night = |day| NaiveDate::from_ymd(1518, 11, day);
+        assert_eq!(
+            schedule.minutes_asleep_by_night(),
+            vec![
+                (night(1), 10, 45),
+                (night(2), 99, 10),
+                (night(3), 10, 5),
+                (night(4), 99, 10),
+                (night(5), 99, 10),
+            ]
+        );
+
+        let late: Vec<_> = schedule.shifts_spanning_midnight().collect();
+        assert_eq!(late.len(), 1);
+        assert_eq!((late[0].guard, late[0].night()), (99, night(2)));
+
+        assert_eq!(schedule.histogram(10)[24], 2);
+        assert_eq!(schedule.histogram(10).iter().sum::<u32>(), 50);
+        assert_eq!(schedule.histogram(99)[45], 3);
+        assert_eq!(schedule.histogram(7), [0; 60]);
+    }
+
+    #[test]
+    fn csv() {
+        let csv = Schedule::from(EXAMPLE).unwrap().to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("guard,0,1,2,"));
+        assert!(lines[0].ends_with(",58,59"));
+        let row: Vec<u32> = lines[2].split(',').map(|c| c.parse().unwrap()).collect();
+        assert_eq!(row.len(), 61);
+        assert_eq!(
+            (row[0], row[1 + 45], row[1 + 35], row[1 + 36]),
+            (99, 3, 0, 1)
+        );
+    }
+
+    #[test]
+    fn invalid_schedules() {
+        assert!(Schedule::from("[1518-11-01 00:05] falls asleep").is_err());
+        assert!(Schedule::from(
+            "[1518-11-01 00:00] Guard #10 begins shift\n[1518-11-01 00:25] wakes up"
+        )
+        .is_err());
+    }
+
     #[test]
     fn test_answer() {
         assert_eq!(answer(), (String::from("8421"), String::from("83359")));
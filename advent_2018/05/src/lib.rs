@@ -5,6 +5,8 @@
 
 extern crate test;
 
+use std::io::{self, BufRead, BufReader, Read};
+
 use rayon::prelude::*;
 
 const INPUT: &str = include_str!("../input");
@@ -17,85 +19,73 @@ pub fn answer() -> (String, String) {
     )
 }
 
+/// A polymer after all its reactions have happened.
 #[derive(Debug, PartialEq, Clone)]
-struct Polymer {
-    units: Vec<char>,
+pub struct Polymer {
+    units: Vec<u8>,
 }
 
 impl Polymer {
-    fn from(s: &str) -> Self {
-        Polymer {
-            units: s.trim().chars().collect(),
+    pub fn from(s: &str) -> Self {
+        // Reading from a slice can't fail.
+        Self::read(s.as_bytes()).unwrap()
+    }
+
+    /// React a polymer as it's read, so only the units that survive are ever held in memory.
+    /// Whitespace is ignored.
+    pub fn read<R: Read>(reader: R) -> io::Result<Self> {
+        let mut reader = BufReader::with_capacity(1 << 16, reader);
+        let mut units = Vec::new();
+        loop {
+            let buf = reader.fill_buf()?;
+            if buf.is_empty() {
+                break;
+            }
+            for &unit in buf.iter().filter(|unit| !unit.is_ascii_whitespace()) {
+                add_unit(&mut units, unit);
+            }
+            let consumed = buf.len();
+            reader.consume(consumed);
         }
+        Ok(Polymer { units })
+    }
+
+    /// The length of the polymer after reacting.
+    pub fn react(&self) -> usize {
+        self.units.len()
     }
 
-    fn remove_react(&self) -> usize {
-        const ALPHABET: [(char, char); 26] = [
-            ('A', 'a'),
-            ('B', 'b'),
-            ('C', 'c'),
-            ('D', 'd'),
-            ('E', 'e'),
-            ('F', 'f'),
-            ('G', 'g'),
-            ('H', 'h'),
-            ('I', 'i'),
-            ('J', 'j'),
-            ('K', 'k'),
-            ('L', 'l'),
-            ('M', 'm'),
-            ('N', 'n'),
-            ('O', 'o'),
-            ('P', 'p'),
-            ('Q', 'q'),
-            ('R', 'r'),
-            ('S', 's'),
-            ('T', 't'),
-            ('U', 'u'),
-            ('V', 'v'),
-            ('W', 'w'),
-            ('X', 'x'),
-            ('Y', 'y'),
-            ('Z', 'z'),
-        ];
-
-        ALPHABET
-            .par_iter()
-            .map(|(lower, upper)| {
-                let mut polymer = self.clone();
-                polymer.units.retain(|&c| c != *lower && c != *upper);
-                polymer.react()
+    /// The length of the shortest polymer made by removing every unit of one type and reacting
+    /// what's left.
+    ///
+    /// Removing a type can only make more units react, so starting from the reacted polymer
+    /// gives the same result as starting from the original one. Each type is tried on its own
+    /// thread.
+    pub fn remove_react(&self) -> usize {
+        (b'a'..b'z' + 1)
+            .into_par_iter()
+            .map(|removed| {
+                let mut units = Vec::with_capacity(self.units.len());
+                for &unit in &self.units {
+                    if unit.to_ascii_lowercase() != removed {
+                        add_unit(&mut units, unit);
+                    }
+                }
+                units.len()
             })
             .min()
             .unwrap()
     }
+}
 
-    fn react(&self) -> usize {
-        let unit_max = self.units.len() - 1;
-        let mut done = Vec::with_capacity(unit_max + 1);
-        done.push(self.units[0]);
-        let mut i = 1;
-        let mut done_i = 0;
-        while i <= unit_max {
-            if done[done_i] != self.units[i]
-                && done[done_i].to_lowercase().next().unwrap()
-                    == self.units[i].to_lowercase().next().unwrap()
-            {
-                done.pop();
-                if done_i == 0 {
-                    done.push(self.units[i + 1]);
-                    i += 2;
-                } else {
-                    done_i -= 1;
-                    i += 1;
-                }
-            } else {
-                done.push(self.units[i]);
-                done_i += 1;
-                i += 1;
-            }
+/// Add a unit to the end of a reacted polymer, reacting it with the last unit if they're the
+/// same type with opposite polarities.
+fn add_unit(units: &mut Vec<u8>, unit: u8) {
+    match units.last() {
+        Some(&last) if last != unit && last.eq_ignore_ascii_case(&unit) => {
+            units.pop();
         }
-        done_i + 1
+        _ => units.push(unit),
     }
 }
 
@@ -111,6 +101,23 @@ mod tests {
         assert_eq!(Polymer::from(input).remove_react(), 4);
     }
 
+    #[test]
+    fn streaming() {
+        let polymer = Polymer::read("dabAcC\naCBAcCcaDA\n".as_bytes()).unwrap();
+        assert_eq!(polymer, Polymer::from("dabAcCaCBAcCcaDA"));
+        assert_eq!(polymer.units, b"dabCBAcaDA");
+
+        // Everything reacts once the second half arrives, long after the first buffer is read.
+        let n = 5_000_000;
+        let reader = io::repeat(b'x').take(n).chain(io::repeat(b'X').take(n));
+        assert_eq!(Polymer::read(reader).unwrap().react(), 0);
+
+        let reader = "aB".as_bytes().chain(io::repeat(b'b').take(n));
+        let polymer = Polymer::read(reader).unwrap();
+        assert_eq!(polymer.react(), n as usize);
+        assert_eq!(polymer.remove_react(), 1);
+    }
+
     #[test]
     fn test_answer() {
         assert_eq!(answer(), (String::from("10598"), String::from("5312")));
@@ -137,5 +144,4 @@ mod tests {
     fn bench_second_answer(b: &mut Bencher) {
         b.iter(|| Polymer::from(INPUT).remove_react())
     }
-
 }
@@ -1,11 +1,17 @@
 #![feature(external_doc)]
 #![doc(include = "../Question.md")]
 
-use std::cmp::{max, min};
-use std::collections::{HashMap, HashSet};
+pub mod metric;
+pub mod ownership;
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
 use std::str::FromStr;
 
-use failure::{bail, Error};
+use failure::{bail, format_err, Error};
+
+use crate::metric::{Manhattan, Metric};
+use crate::ownership::{Owner, OwnershipMap};
 
 const INPUT: &str = include_str!("../input");
 
@@ -17,24 +23,21 @@ pub fn answer() -> (String, String) {
     )
 }
 
+/// A set of coordinates, and the metric used to measure distances between points.
 #[derive(Debug)]
-struct Grid {
+pub struct Grid<M = Manhattan> {
     coords: Vec<Point>,
     max: Point,
+    metric: M,
 }
 
 #[derive(Debug, PartialEq, Copy, Clone, Eq, Hash)]
-struct Point {
-    x: u32,
-    y: u32,
+pub struct Point {
+    pub x: u32,
+    pub y: u32,
 }
 
 impl Point {
-    /// Finds the Manhattan distance between two points.
-    fn distance(self, p: Self) -> u32 {
-        max(self.x, p.x) - min(self.x, p.x) + max(self.y, p.y) - min(self.y, p.y)
-    }
-
     /// Returns the four adjacent points to the current point (above, below, to the left,
     /// and to the right).
     fn adjacent(self) -> [Point; 4] {
@@ -59,8 +62,8 @@ impl Point {
     }
 }
 
-impl Grid {
-    fn from(s: &str) -> Result<Self, Error> {
+impl Grid<Manhattan> {
+    pub fn from(s: &str) -> Result<Self, Error> {
         let (coords, errors): (Vec<_>, Vec<_>) = s
             .lines()
             .map(|l| l.parse::<Point>())
@@ -72,23 +75,67 @@ impl Grid {
         if !errors.is_empty() {
             bail!("Parsing errors: {:?}", errors);
         }
+        if coords.is_empty() {
+            bail!("No coordinates found");
+        }
 
         let max = Point {
             x: coords.iter().max_by_key(|p| p.x).unwrap().x,
             y: coords.iter().max_by_key(|p| p.y).unwrap().y,
         };
 
-        Ok(Grid { coords, max })
+        Ok(Grid {
+            coords,
+            max,
+            metric: Manhattan,
+        })
     }
+}
 
-    /// Given a point `point`, finds the coordinate in `self.coords` with the minimum Manhattan
-    /// distance to the point.
-    fn closest(&self, point: Point) -> Point {
-        *self
+impl<M: Metric> Grid<M> {
+    /// The same coordinates, measured with a different metric.
+    pub fn with_metric<N: Metric>(self, metric: N) -> Grid<N> {
+        Grid {
+            coords: self.coords,
+            max: self.max,
+            metric,
+        }
+    }
+
+    /// Given a point `point`, finds the coordinate in `self.coords` with the minimum distance to
+    /// the point, if there's only one.
+    pub fn owner(&self, point: Point) -> Owner {
+        owner(&self.metric, &self.coords, point)
+    }
+
+    /// The owner of every point in a rectangle containing all the coordinates and every finite
+    /// region, as given by the metric.
+    pub fn ownership(&self) -> Result<OwnershipMap, Error> {
+        self.moved_ownership(0, 0)
+    }
+
+    /// The same as `ownership`, with every coordinate moved by `(dx, dy)` first.
+    fn moved_ownership(&self, dx: i64, dy: i64) -> Result<OwnershipMap, Error> {
+        let moved = |(x, y): (i64, i64)| match (u32::try_from(x + dx), u32::try_from(y + dy)) {
+            (Ok(x), Ok(y)) => Ok(Point { x, y }),
+            _ => Err(format_err!("({}, {}) is off the grid", x + dx, y + dy)),
+        };
+        let coords = self
             .coords
             .iter()
-            .min_by_key(|p| p.distance(point))
-            .unwrap()
+            .map(|p| moved((i64::from(p.x), i64::from(p.y))))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let (min, max) = self.metric.finite_bounds(&self.coords);
+        let infinite = self.metric.infinite_regions(&coords);
+        OwnershipMap::new(&coords, infinite, moved(min)?, moved(max)?, |p| {
+            owner(&self.metric, &coords, p)
+        })
+    }
+
+    /// The owner of every point from `min` to `max` inclusive.
+    pub fn ownership_in(&self, min: Point, max: Point) -> Result<OwnershipMap, Error> {
+        let infinite = self.metric.infinite_regions(&self.coords);
+        OwnershipMap::new(&self.coords, infinite, min, max, |p| self.owner(p))
     }
 
     /// Finds the points adjacent to `p` that are on the grid.
@@ -100,57 +147,28 @@ impl Grid {
             .collect()
     }
 
-    /// Finds the total Manhattan distance between all coords in the grid and `point`.
-    fn total_distance(&self, point: Point) -> u32 {
-        self.coords.iter().fold(0, |acc, p| acc + p.distance(point))
-    }
-
-    fn most_remote(&self) -> Option<u32> {
-        let coord_set: HashSet<Point> = self.coords.iter().cloned().collect();
-        let mut infinite: HashSet<Point> = HashSet::new();
-        let mut sizes: HashMap<Point, u32> = HashMap::new();
-        let mut checked: HashSet<Point> = coord_set.clone();
-
-        // Loop top and bottom edges.
-        for &y in &[1_u32, self.max.y] {
-            for x in 1..=self.max.x {
-                infinite.insert(self.closest(Point { x, y }));
-                checked.insert(Point { x, y });
-            }
-        }
-
-        // Loop left and right edges.
-        for &x in &[1_u32, self.max.x] {
-            for y in 2..self.max.y {
-                infinite.insert(self.closest(Point { x, y }));
-                checked.insert(Point { x, y });
-            }
-        }
-
-        let mut to_check: Vec<(Point, Point)> = self
-            .coords
+    /// Finds the total distance between all coords in the grid and `point`.
+    fn total_distance(&self, point: Point) -> f64 {
+        self.coords
             .iter()
-            .filter(|p| !infinite.contains(p))
-            .map(|p| (*p, *p))
-            .collect();
-
-        while let Some((p, p_closest)) = to_check.pop() {
-            for adj_p in self.adjacent(p) {
-                if !checked.contains(&adj_p) {
-                    let closest = self.closest(adj_p);
-                    (*sizes.entry(closest).or_insert(1)) += 1;
-                    checked.insert(adj_p);
-                    if p_closest == closest {
-                        to_check.push((adj_p, closest));
-                    }
-                }
-            }
-        }
+            .map(|&p| self.metric.distance(p, point))
+            .sum()
+    }
 
-        sizes.values().max().cloned()
+    /// The size of the largest region that isn't infinite.
+    pub fn most_remote(&self) -> Option<u32> {
+        // Moving every coordinate doesn't change the regions, so make room if finite regions
+        // reach past the top or left of the grid.
+        let ((min_x, min_y), _) = self.metric.finite_bounds(&self.coords);
+        self.moved_ownership((-min_x).max(0), (-min_y).max(0))
+            .ok()?
+            .largest_finite()
+            .map(|size| size as u32)
     }
 
-    fn busiest_region(&self, total_distance: u32) -> Option<u32> {
+    /// The size of the region of points whose total distance to all the coords is at most
+    /// `total_distance`.
+    pub fn busiest_region(&self, total_distance: u32) -> Option<u32> {
         let mut to_check: Vec<Point> = Vec::new();
         let mut checked: HashSet<Point> = HashSet::new();
         let mut region_size = 0;
@@ -181,7 +199,7 @@ impl Grid {
         }
 
         while let Some(p) = to_check.pop() {
-            if self.total_distance(p) <= total_distance && !checked.contains(&p) {
+            if self.total_distance(p) <= f64::from(total_distance) && !checked.contains(&p) {
                 checked.insert(p);
                 region_size += 1;
                 for adj_p in self.adjacent(p) {
@@ -196,6 +214,20 @@ impl Grid {
     }
 }
 
+/// The only seed closest to `point`, if there is one.
+fn owner<M: Metric>(metric: &M, seeds: &[Point], point: Point) -> Owner {
+    let mut closest = (u64::MAX, Owner::Tie);
+    for (seed, &p) in seeds.iter().enumerate() {
+        let key = metric.key(p, point);
+        if key < closest.0 {
+            closest = (key, Owner::Seed(seed));
+        } else if key == closest.0 {
+            closest.1 = Owner::Tie;
+        }
+    }
+    closest.1
+}
+
 impl FromStr for Point {
     type Err = Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -219,21 +251,104 @@ impl FromStr for Point {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::metric::{Chebyshev, Euclidean};
+
+    const EXAMPLE: &str = "1, 1\n\
+                           1, 6\n\
+                           8, 3\n\
+                           3, 4\n\
+                           5, 5\n\
+                           8, 9\n";
 
     #[test]
     fn examples() {
-        let input = "1, 1\n\
-                     1, 6\n\
-                     8, 3\n\
-                     3, 4\n\
-                     5, 5\n\
-                     8, 9\n";
-
-        let grid = Grid::from(input).unwrap();
+        let grid = Grid::from(EXAMPLE).unwrap();
         assert_eq!(grid.most_remote().unwrap(), 17);
         assert_eq!(grid.busiest_region(30).unwrap(), 16);
     }
 
+    #[test]
+    fn ownership_map() {
+        let grid = Grid::from(EXAMPLE).unwrap();
+        let map = grid
+            .ownership_in(Point { x: 0, y: 0 }, Point { x: 9, y: 9 })
+            .unwrap();
+        assert_eq!(
+            map.to_ascii(),
+            "aaaaa.cccc\n\
+             aAaaa.cccc\n\
+             aaaddecccc\n\
+             aadddeccCc\n\
+             ..dDdeeccc\n\
+             bb.deEeecc\n\
+             bBb.eeee..\n\
+             bbb.eeefff\n\
+             bbb.eeffff\n\
+             bbb.ffffFf\n"
+        );
+        assert_eq!(map.owner(Point { x: 5, y: 2 }), Some(Owner::Seed(4)));
+        assert_eq!(map.owner(Point { x: 0, y: 4 }), Some(Owner::Tie));
+        assert_eq!(map.owner(Point { x: 10, y: 4 }), None);
+        assert_eq!(map.sizes()[3], 9);
+        assert_eq!(
+            (0..6).map(|seed| map.is_infinite(seed)).collect::<Vec<_>>(),
+            vec![true, true, true, false, false, true]
+        );
+
+        let ppm = map.to_ppm();
+        assert!(ppm.starts_with("P3\n10 10\n255\n"));
+        assert_eq!(ppm.lines().count(), 13);
+        // Seed A at (1, 1) is white, and the tie at (5, 0) is black.
+        assert_eq!(ppm.lines().nth(3).unwrap().split(' ').nth(15), Some("0"));
+        assert_eq!(
+            &ppm.lines().nth(4).unwrap().split(' ').collect::<Vec<_>>()[3..6],
+            ["255"; 3]
+        );
+
+        assert!(grid
+            .ownership_in(Point { x: 5, y: 0 }, Point { x: 4, y: 9 })
+            .is_err());
+        let no_seeds = |p| OwnershipMap::new(&[], vec![], p, Point { x: 0, y: 0 }, |_| Owner::Tie);
+        assert!(no_seeds(Point { x: 1, y: 1 }).is_err());
+        assert_eq!(no_seeds(Point { x: 0, y: 0 }).unwrap().sizes(), vec![]);
+    }
+
+    #[test]
+    fn metrics() {
+        let grid = Grid::from(EXAMPLE).unwrap().with_metric(Chebyshev);
+        assert_eq!(grid.owner(Point { x: 8, y: 8 }), Owner::Seed(5));
+        assert_eq!(grid.owner(Point { x: 2, y: 5 }), Owner::Tie);
+        assert_eq!(grid.most_remote(), Some(10));
+
+        let grid = grid.with_metric(Euclidean);
+        assert_eq!(grid.owner(Point { x: 1, y: 4 }), Owner::Tie);
+        assert_eq!(grid.owner(Point { x: 4, y: 4 }), Owner::Seed(3));
+
+        let grid = Grid::from("1, 189\n99, 161\n")
+            .unwrap()
+            .with_metric(Euclidean);
+        assert_eq!(grid.owner(Point { x: 0, y: 0 }), Owner::Tie);
+    }
+
+    #[test]
+    fn finite_regions_past_the_bounding_box() {
+        let grid = Grid::from("100, 100\n110, 100\n105, 110\n105, 101\n").unwrap();
+        assert_eq!(grid.most_remote(), None);
+        let grid = grid.with_metric(Chebyshev);
+        assert_eq!(grid.most_remote(), Some(45));
+        let grid = grid.with_metric(Euclidean);
+        assert_eq!(grid.most_remote(), Some(59));
+        let map = grid.ownership().unwrap();
+        assert_eq!(map.owner(Point { x: 105, y: 89 }), Some(Owner::Seed(3)));
+
+        // The same regions, with the last one reaching past the top of the grid.
+        let grid = Grid::from("0, 10\n10, 10\n5, 20\n5, 11\n")
+            .unwrap()
+            .with_metric(Euclidean);
+        assert_eq!(grid.most_remote(), Some(59));
+        assert!(grid.ownership().is_err());
+    }
+
     #[test]
     fn test_answer() {
         assert_eq!(answer(), (String::from("4011"), String::from("46054")));
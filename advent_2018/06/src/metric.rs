@@ -0,0 +1,345 @@
+//! Ways of measuring the distance between two points on the grid.
+
+use crate::Point;
+
+pub trait Metric {
+    /// A measure of distance that compares exactly: of two points, the closer one has the
+    /// smaller key, and points the same distance away have the same key.
+    fn key(&self, a: Point, b: Point) -> u64;
+
+    fn distance(&self, a: Point, b: Point) -> f64;
+
+    /// Whether each seed owns infinitely many cells. Depending on the metric, a region can reach
+    /// the edge of the seeds' bounding box and still be finite, or the other way round.
+    fn infinite_regions(&self, seeds: &[Point]) -> Vec<bool>;
+
+    /// The corners (top left, then bottom right) of a rectangle holding every seed and every
+    /// cell of every finite region. Depending on the metric, finite regions can reach past the
+    /// seeds' bounding box.
+    fn finite_bounds(&self, seeds: &[Point]) -> ((i64, i64), (i64, i64));
+}
+
+/// Distance along the grid lines: `|dx| + |dy|`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Manhattan;
+
+/// Distance for a king on a chess board: `max(|dx|, |dy|)`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Chebyshev;
+
+/// Straight-line distance: `√(dx² + dy²)`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Euclidean;
+
+/// The horizontal and vertical distances between two points.
+fn deltas(a: Point, b: Point) -> (u64, u64) {
+    (
+        u64::from(a.x.max(b.x) - a.x.min(b.x)),
+        u64::from(a.y.max(b.y) - a.y.min(b.y)),
+    )
+}
+
+/// The corners of the seeds' bounding box, or an empty box if there are no seeds.
+fn bounding_box(seeds: &[Point]) -> ((i64, i64), (i64, i64)) {
+    let xs = seeds.iter().map(|p| i64::from(p.x));
+    let ys = seeds.iter().map(|p| i64::from(p.y));
+    (
+        (xs.clone().min().unwrap_or(0), ys.clone().min().unwrap_or(0)),
+        (xs.max().unwrap_or(-1), ys.max().unwrap_or(-1)),
+    )
+}
+
+/// The seeds' coordinates after turning the grid through `turns` quarter turns, so that each
+/// side (and corner) in turn becomes the left side (and bottom left corner).
+fn turned(seeds: &[Point], turns: usize) -> Vec<(i64, i64)> {
+    seeds
+        .iter()
+        .map(|p| {
+            let (x, y) = (i64::from(p.x), i64::from(p.y));
+            match turns % 4 {
+                0 => (x, y),
+                1 => (-y, x),
+                2 => (-x, -y),
+                _ => (y, -x),
+            }
+        })
+        .collect()
+}
+
+/// The index of the seed with the smallest key, unless another seed's key is just as small.
+fn unique_min<F: Fn((i64, i64)) -> i64>(seeds: &[(i64, i64)], key: F) -> Option<usize> {
+    let mut best: Option<(i64, usize)> = None;
+    let mut tied = false;
+    for (seed, &p) in seeds.iter().enumerate() {
+        let k = key(p);
+        match best {
+            Some((least, _)) if k > least => {}
+            Some((least, _)) if k == least => tied = true,
+            _ => {
+                best = Some((k, seed));
+                tied = false;
+            }
+        }
+    }
+    if tied {
+        None
+    } else {
+        best.map(|(_, seed)| seed)
+    }
+}
+
+impl Metric for Manhattan {
+    fn key(&self, a: Point, b: Point) -> u64 {
+        let (dx, dy) = deltas(a, b);
+        dx + dy
+    }
+
+    fn distance(&self, a: Point, b: Point) -> f64 {
+        self.key(a, b) as f64
+    }
+
+    /// Far to the left of every seed, level with row `y`, each seed's distance is
+    /// `x + |y - row|` plus the same amount, so the seed with the smallest such value owns the
+    /// rest of the row. Above the top row or below the bottom one, it's `x - y` or `x + y`
+    /// instead, which picks the same seed as the top or bottom row does.
+    fn infinite_regions(&self, seeds: &[Point]) -> Vec<bool> {
+        let mut infinite = vec![false; seeds.len()];
+        for turns in 0..4 {
+            let seeds = turned(seeds, turns);
+            let rows = seeds.iter().map(|&(_, y)| y);
+            for row in rows.clone().min().unwrap_or(0)..=rows.max().unwrap_or(-1) {
+                if let Some(seed) = unique_min(&seeds, |(x, y)| x + (y - row).abs()) {
+                    infinite[seed] = true;
+                }
+            }
+        }
+        infinite
+    }
+
+    /// A cell outside the seeds' bounding box can be moved towards it, one step at a time,
+    /// bringing every seed one step closer. It keeps the same owner all the way, so regions
+    /// that leave the box reach its edge and are infinite.
+    fn finite_bounds(&self, seeds: &[Point]) -> ((i64, i64), (i64, i64)) {
+        bounding_box(seeds)
+    }
+}
+
+impl Metric for Chebyshev {
+    fn key(&self, a: Point, b: Point) -> u64 {
+        let (dx, dy) = deltas(a, b);
+        dx.max(dy)
+    }
+
+    fn distance(&self, a: Point, b: Point) -> f64 {
+        self.key(a, b) as f64
+    }
+
+    /// Below and to the left of every seed, on the diagonal line `y = x + c`, each seed's
+    /// distance is `max(x, y - c)` plus the same amount, so the seed with the smallest such value
+    /// owns the rest of the line. Outside the range of `c` tried here, it's always `x` (or
+    /// always `y - c`), which picks the same seed as the end of the range does.
+    fn infinite_regions(&self, seeds: &[Point]) -> Vec<bool> {
+        let mut infinite = vec![false; seeds.len()];
+        for turns in 0..4 {
+            let seeds = turned(seeds, turns);
+            let offsets = seeds.iter().map(|&(x, y)| y - x);
+            for c in offsets.clone().min().unwrap_or(0)..=offsets.max().unwrap_or(-1) {
+                if let Some(seed) = unique_min(&seeds, |(x, y)| x.max(y - c)) {
+                    infinite[seed] = true;
+                }
+            }
+        }
+        infinite
+    }
+
+    /// Chebyshev distance is half the Manhattan distance in the coordinates `x + y` and
+    /// `x - y`, so (as for Manhattan distance) finite regions stay inside the seeds' bounding
+    /// box in those coordinates. That's a diamond, which can stick out of the ordinary bounding
+    /// box by half its height on the left and right, and half its width at the top and bottom.
+    fn finite_bounds(&self, seeds: &[Point]) -> ((i64, i64), (i64, i64)) {
+        let ((min_x, min_y), (max_x, max_y)) = bounding_box(seeds);
+        let (pad_x, pad_y) = ((max_y - min_y + 1) / 2, (max_x - min_x + 1) / 2);
+        (
+            (min_x - pad_x, min_y - pad_y),
+            (max_x + pad_x, max_y + pad_y),
+        )
+    }
+}
+
+impl Metric for Euclidean {
+    /// The square of the distance, which (unlike the distance itself) is a whole number.
+    fn key(&self, a: Point, b: Point) -> u64 {
+        let (dx, dy) = deltas(a, b);
+        dx * dx + dy * dy
+    }
+
+    fn distance(&self, a: Point, b: Point) -> f64 {
+        (self.key(a, b) as f64).sqrt()
+    }
+
+    /// A seed owns infinitely many cells if it's on the boundary of the seeds' convex hull (and
+    /// no other seed is in the same place). Then the other seeds all lie on one side of a line
+    /// through it, and it owns the cells going straight out from the other side.
+    fn infinite_regions(&self, seeds: &[Point]) -> Vec<bool> {
+        let seeds = turned(seeds, 0);
+        (0..seeds.len())
+            .map(|seed| {
+                let (sx, sy) = seeds[seed];
+                let others: Vec<(i64, i64)> = (0..seeds.len())
+                    .filter(|&other| other != seed)
+                    .map(|other| (seeds[other].0 - sx, seeds[other].1 - sy))
+                    .collect();
+                if others.contains(&(0, 0)) {
+                    return false;
+                }
+                // A half-plane holding the others can be turned until its edge passes through
+                // one of them.
+                others.is_empty()
+                    || others.iter().any(|&(ax, ay)| {
+                        let cross = |&(bx, by): &(i64, i64)| ax * by - ay * bx;
+                        others.iter().all(|b| cross(b) >= 0) || others.iter().all(|b| cross(b) <= 0)
+                    })
+            })
+            .collect()
+    }
+
+    /// Each finite region is a polygon: the part of the plane on the seed's side of the line
+    /// halfway to every other seed. Its corners are the centres of circles through the seed and
+    /// two others, which are no further from the seed than the cube of the distances between
+    /// seeds. So cutting a square that size down to the polygon gives its extent.
+    fn finite_bounds(&self, seeds: &[Point]) -> ((i64, i64), (i64, i64)) {
+        let ((mut min_x, mut min_y), (mut max_x, mut max_y)) = bounding_box(seeds);
+        let reach = (max_x - min_x + max_y - min_y + 1).pow(3) as f64;
+        let infinite = self.infinite_regions(seeds);
+        let seeds = turned(seeds, 0);
+        for (seed, &(sx, sy)) in seeds.iter().enumerate() {
+            // Seeds sharing a place with another own nothing at all.
+            let shared = seeds.iter().filter(|&&q| q == (sx, sy)).count() > 1;
+            if infinite[seed] || shared {
+                continue;
+            }
+            let (sx, sy) = (sx as f64, sy as f64);
+            let mut polygon = vec![
+                (sx - reach, sy - reach),
+                (sx + reach, sy - reach),
+                (sx + reach, sy + reach),
+                (sx - reach, sy + reach),
+            ];
+            for &(qx, qy) in &seeds {
+                let (qx, qy) = (qx as f64, qy as f64);
+                // The points at least as close to the seed as to q.
+                let side = |(x, y): (f64, f64)| {
+                    2.0 * ((qx - sx) * x + (qy - sy) * y) - (qx * qx + qy * qy - sx * sx - sy * sy)
+                };
+                if (qx, qy) != (sx, sy) {
+                    polygon = clip(&polygon, side);
+                }
+            }
+            for &(x, y) in &polygon {
+                // Allow for rounding errors in the corners.
+                min_x = min_x.min(x.floor() as i64 - 1);
+                min_y = min_y.min(y.floor() as i64 - 1);
+                max_x = max_x.max(x.ceil() as i64 + 1);
+                max_y = max_y.max(y.ceil() as i64 + 1);
+            }
+        }
+        ((min_x, min_y), (max_x, max_y))
+    }
+}
+
+/// The part of a convex polygon where `side` isn't positive, given that `side` is linear.
+fn clip<F: Fn((f64, f64)) -> f64>(polygon: &[(f64, f64)], side: F) -> Vec<(f64, f64)> {
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for (i, &a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        let (side_a, side_b) = (side(a), side(b));
+        if side_a <= 0.0 {
+            clipped.push(a);
+        }
+        if (side_a < 0.0 && side_b > 0.0) || (side_a > 0.0 && side_b < 0.0) {
+            let t = side_a / (side_a - side_b);
+            clipped.push((a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1)));
+        }
+    }
+    clipped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        let (a, b) = (Point { x: 1, y: 7 }, Point { x: 4, y: 3 });
+        assert_eq!(Manhattan.distance(a, b), 7.0);
+        assert_eq!(Chebyshev.distance(a, b), 4.0);
+        assert_eq!(Euclidean.distance(a, b), 5.0);
+        assert_eq!(Euclidean.distance(b, a), 5.0);
+        assert_eq!(Euclidean.key(a, b), 25);
+    }
+
+    #[test]
+    fn exact_keys() {
+        // The same distance from the origin, though `hypot` rounds them differently.
+        let (origin, a, b) = (
+            Point { x: 0, y: 0 },
+            Point { x: 1, y: 189 },
+            Point { x: 99, y: 161 },
+        );
+        assert_eq!(Euclidean.key(origin, a), Euclidean.key(origin, b));
+        assert_eq!(Euclidean.distance(origin, a), Euclidean.distance(origin, b));
+    }
+
+    #[test]
+    fn finite_bounds() {
+        let seeds = points(&[(100, 100), (110, 100), (105, 110), (105, 101)]);
+        assert_eq!(Manhattan.finite_bounds(&seeds), ((100, 100), (110, 110)));
+        assert_eq!(Chebyshev.finite_bounds(&seeds), ((95, 95), (115, 115)));
+        let ((min_x, min_y), (max_x, max_y)) = Euclidean.finite_bounds(&seeds);
+        assert!(min_x <= 100 && max_x >= 110 && max_y >= 110);
+        // The fourth seed's region reaches down to (105, 88), which is as far from it as from
+        // the first two seeds.
+        assert!(min_y == 86 || min_y == 87);
+        assert!(Euclidean.finite_bounds(&[]).0 > Euclidean.finite_bounds(&[]).1);
+
+        // Seeds in the same place own nothing, so they don't stretch the bounds.
+        let seeds = points(&[(0, 0), (4, 0), (0, 4), (4, 4), (2, 2), (2, 2)]);
+        assert_eq!(Euclidean.finite_bounds(&seeds), ((0, 0), (4, 4)));
+    }
+
+    fn points(coords: &[(u32, u32)]) -> Vec<Point> {
+        coords.iter().map(|&(x, y)| Point { x, y }).collect()
+    }
+
+    #[test]
+    fn infinite_regions() {
+        // The third seed owns (5, 0) on the edge of the bounding box, but only Manhattan
+        // distance lets it keep going downwards.
+        let seeds = points(&[(0, 0), (10, 0), (5, 1), (5, 10)]);
+        assert_eq!(Manhattan.infinite_regions(&seeds), [true, true, true, true]);
+        assert_eq!(
+            Chebyshev.infinite_regions(&seeds),
+            [true, true, false, true]
+        );
+        assert_eq!(
+            Euclidean.infinite_regions(&seeds),
+            [true, true, false, true]
+        );
+
+        // Straight up from the middle seed, the Chebyshev distances to all three are the same.
+        let seeds = points(&[(0, 0), (5, 0), (10, 0)]);
+        assert_eq!(Manhattan.infinite_regions(&seeds), [true, true, true]);
+        assert_eq!(Chebyshev.infinite_regions(&seeds), [true, false, true]);
+        assert_eq!(Euclidean.infinite_regions(&seeds), [true, true, true]);
+
+        let seeds = points(&[(0, 0), (4, 0), (0, 4), (4, 4), (2, 2), (4, 4)]);
+        for infinite in &[
+            Manhattan.infinite_regions(&seeds),
+            Chebyshev.infinite_regions(&seeds),
+            Euclidean.infinite_regions(&seeds),
+        ] {
+            assert_eq!(infinite, &[true, true, true, false, false, false]);
+        }
+        assert!(Euclidean.infinite_regions(&[]).is_empty());
+    }
+}
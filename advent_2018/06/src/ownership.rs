@@ -0,0 +1,183 @@
+//! Which seed coordinate each cell of the grid is closest to, and pictures of the result.
+
+use std::fmt::Write;
+
+use failure::{ensure, Error};
+
+use crate::Point;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Owner {
+    /// The cell is closest to this seed (an index into the grid's coordinates).
+    Seed(usize),
+    /// The cell is equally close to two or more seeds, so belongs to none of them.
+    Tie,
+}
+
+/// The owner of every cell in a rectangle of the grid.
+#[derive(Debug, Clone)]
+pub struct OwnershipMap {
+    pub min: Point,
+    pub max: Point,
+    seeds: Vec<Point>,
+    /// Owners in reading order (left to right, then top to bottom).
+    cells: Vec<Owner>,
+    /// Whether each seed owns infinitely many cells, inside the map or not.
+    infinite: Vec<bool>,
+}
+
+impl OwnershipMap {
+    /// Work out the owner of every cell from `min` to `max` inclusive. `infinite` says which
+    /// seeds own infinitely many cells, as the map can't tell by itself.
+    pub fn new<F: Fn(Point) -> Owner>(
+        seeds: &[Point],
+        infinite: Vec<bool>,
+        min: Point,
+        max: Point,
+        owner: F,
+    ) -> Result<Self, Error> {
+        ensure!(
+            min.x <= max.x && min.y <= max.y,
+            "The map from {:?} to {:?} is empty",
+            min,
+            max
+        );
+        ensure!(
+            infinite.len() == seeds.len(),
+            "Infinite flags for {} seeds, but there are {}",
+            infinite.len(),
+            seeds.len()
+        );
+        let mut cells = Vec::new();
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                cells.push(owner(Point { x, y }));
+            }
+        }
+        Ok(OwnershipMap {
+            min,
+            max,
+            seeds: seeds.to_vec(),
+            cells,
+            infinite,
+        })
+    }
+
+    fn width(&self) -> usize {
+        (self.max.x - self.min.x + 1) as usize
+    }
+
+    /// The owner of `point`, or `None` if it's outside the map.
+    pub fn owner(&self, point: Point) -> Option<Owner> {
+        if point.x < self.min.x
+            || point.x > self.max.x
+            || point.y < self.min.y
+            || point.y > self.max.y
+        {
+            return None;
+        }
+        let (x, y) = (
+            (point.x - self.min.x) as usize,
+            (point.y - self.min.y) as usize,
+        );
+        Some(self.cells[y * self.width() + x])
+    }
+
+    /// Whether `seed` owns infinitely many cells.
+    pub fn is_infinite(&self, seed: usize) -> bool {
+        self.infinite[seed]
+    }
+
+    /// The number of cells each seed owns.
+    pub fn sizes(&self) -> Vec<usize> {
+        let mut sizes = vec![0; self.seeds.len()];
+        for cell in &self.cells {
+            if let Owner::Seed(seed) = *cell {
+                sizes[seed] += 1;
+            }
+        }
+        sizes
+    }
+
+    /// The size of the largest finite region.
+    pub fn largest_finite(&self) -> Option<usize> {
+        self.sizes()
+            .into_iter()
+            .enumerate()
+            .filter(|&(seed, _)| !self.is_infinite(seed))
+            .map(|(_, size)| size)
+            .max()
+    }
+
+    /// Draw the map as in the puzzle: seeds as capital letters, the cells they own as lower
+    /// case letters, and ties as `.`. Letters are reused after the 26th seed.
+    pub fn to_ascii(&self) -> String {
+        let letter = |seed: usize| (b'a' + (seed % 26) as u8) as char;
+        let mut out = String::with_capacity(self.cells.len() + self.cells.len() / self.width());
+        for (i, cell) in self.cells.iter().enumerate() {
+            let point = self.point(i);
+            out.push(match *cell {
+                Owner::Seed(seed) if self.seeds[seed] == point => letter(seed).to_ascii_uppercase(),
+                Owner::Seed(seed) => letter(seed),
+                Owner::Tie => '.',
+            });
+            if point.x == self.max.x {
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// Draw the map as a plain (P3) PPM image, one pixel per cell. Each region gets its own
+    /// colour, drawn at half brightness if it's infinite. Ties are black and seeds are white.
+    pub fn to_ppm(&self) -> String {
+        let mut out = format!(
+            "P3\n{} {}\n255\n",
+            self.width(),
+            self.cells.len() / self.width()
+        );
+        for (i, cell) in self.cells.iter().enumerate() {
+            let point = self.point(i);
+            let (r, g, b) = match *cell {
+                Owner::Seed(seed) if self.seeds[seed] == point => (255, 255, 255),
+                Owner::Seed(seed) => {
+                    let (r, g, b) = colour(seed);
+                    if self.is_infinite(seed) {
+                        (r / 2, g / 2, b / 2)
+                    } else {
+                        (r, g, b)
+                    }
+                }
+                Owner::Tie => (0, 0, 0),
+            };
+            let separator = if point.x == self.max.x { '\n' } else { ' ' };
+            write!(out, "{} {} {}{}", r, g, b, separator).unwrap();
+        }
+        out
+    }
+
+    /// The point of the `i`th cell.
+    fn point(&self, i: usize) -> Point {
+        Point {
+            x: self.min.x + (i % self.width()) as u32,
+            y: self.min.y + (i / self.width()) as u32,
+        }
+    }
+}
+
+/// A bright colour for a seed, spreading neighbouring seeds' colours around the colour wheel.
+fn colour(seed: usize) -> (u8, u8, u8) {
+    // Step round the hue by the golden angle so that nearby seeds look different.
+    let hue = (seed as f64 * 137.508) % 360.0;
+    let x = 1.0 - ((hue / 60.0) % 2.0 - 1.0).abs();
+    let (r, g, b) = match (hue / 60.0) as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    let scale = |c: f64| (55.0 + c * 200.0) as u8;
+    (scale(r), scale(g), scale(b))
+}
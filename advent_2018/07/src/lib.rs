@@ -1,134 +1,48 @@
 #![feature(external_doc)]
 #![doc(include = "../Question.md")]
-#![feature(vec_remove_item)]
 
-use std::char;
-use std::collections::HashMap;
-use std::mem;
+pub mod scheduler;
+
+use crate::scheduler::Scheduler;
 
 const INPUT: &str = include_str!("../input");
 
 pub fn answer() -> (String, String) {
     let assembly = Assembly::from(INPUT);
-    (
-        assembly.clone().order(),
-        assembly.work_time(5, 60).to_string(),
-    )
+    (assembly.order(), assembly.work_time(5, 60).to_string())
 }
 
-/// Set of assembly instructions (list of which steps are required to be completed before a
-/// given step).
+/// Set of assembly instructions (which steps are required to be completed before a given step).
 #[derive(Debug, Clone)]
 struct Assembly {
-    requirements: HashMap<char, Vec<char>>,
-}
-
-/// Someone working on an assembly. `Worker::Busy` takes a step name (char) and a finishing time (time
-/// when the step will be complete).
-#[derive(Debug, Clone)]
-enum Worker {
-    Busy(char, u32),
-    Idle,
+    steps: Scheduler<char>,
 }
 
 impl Assembly {
     fn from(s: &str) -> Self {
-        let mut requirements = HashMap::with_capacity(26);
+        let mut steps = Scheduler::new();
 
         for line in s.lines() {
             let mut words = line.split_whitespace();
             let required = words.nth(1).unwrap().chars().next().unwrap();
             let step = words.nth(5).unwrap().chars().next().unwrap();
-            requirements
-                .entry(step)
-                .or_insert_with(Vec::new)
-                .push(required);
-            requirements.entry(required).or_insert_with(Vec::new);
+            steps.add_dependency(required, step);
         }
-        Assembly { requirements }
+        Assembly { steps }
     }
 
     /// Return the order in which the instructions should be completed, assuming one
-    /// person works through them in order.
-    fn order(mut self) -> String {
-        let mut out = String::new();
-
-        while !self.requirements.is_empty() {
-            let next = self.get_next().unwrap();
-            self.requirements.remove(&next);
-            out.push(next);
-
-            self.remove_requirement(next);
-        }
-        out
-    }
-
-    /// Get the next instruction to be worked on if there is one. Returns the first
-    /// instruction (alphabetically) without requirements.
-    fn get_next(&self) -> Option<char> {
-        self.requirements
-            .iter()
-            .fold(None, |first_empty, (c, reqs)| {
-                if reqs.is_empty() && (first_empty == None || *c < first_empty.unwrap()) {
-                    Some(*c)
-                } else {
-                    first_empty
-                }
-            })
-    }
-
-    /// Remove a completed requirement from anything which requires it.
-    fn remove_requirement(&mut self, c: char) {
-        for reqs in self.requirements.values_mut() {
-            reqs.remove_item(&c);
-        }
+    /// person works through them in alphabetical order where there's a choice.
+    fn order(&self) -> String {
+        self.steps.schedule(1).unwrap().order().collect()
     }
 
-    /// Work out how long `worker_count` workers would take to finish the assembly.
-    fn work_time(mut self, worker_count: u32, job_delay: u32) -> u32 {
-        let mut time: u32 = 0;
-        let mut workers = vec![Worker::default(); worker_count as usize];
-        while time == 0 || workers.iter().any(|w| w.is_busy()) {
-            for worker in workers.iter_mut() {
-                match worker {
-                    Worker::Busy(c, t) if t == &time => {
-                        self.remove_requirement(*c);
-                        mem::replace(worker, Worker::Idle);
-                    }
-                    _ => {}
-                }
-            }
-
-            for worker in workers.iter_mut() {
-                if let Worker::Idle = worker {
-                    if let Some(next) = self.get_next() {
-                        mem::replace(
-                            worker,
-                            Worker::Busy(next, time + job_delay + next as u32 - 64),
-                        );
-                        self.requirements.remove(&next);
-                    }
-                }
-            }
-            time += 1;
-        }
-        time - 1
-    }
-}
-
-impl Default for Worker {
-    fn default() -> Self {
-        Worker::Idle
-    }
-}
-
-impl Worker {
-    /// Whether the worker is `Worker::Busy`.
-    fn is_busy(&self) -> bool {
-        match self {
-            Worker::Busy(_, _) => true,
-            _ => false,
-        }
+    /// Work out how long `worker_count` workers would take to finish the assembly, if each step
+    /// takes `job_delay` seconds plus its position in the alphabet.
+    fn work_time(&self, worker_count: usize, job_delay: u32) -> u32 {
+        let mut steps = self.steps.clone();
+        steps.set_durations(|&step| job_delay + step as u32 - 'A' as u32 + 1);
+        steps.schedule(worker_count).unwrap().makespan()
     }
 }
 
@@ -149,8 +63,17 @@ mod tests {
                      ";
 
         let assembly = Assembly::from(input);
-        assert_eq!(assembly.clone().order(), "CABDFE");
+        assert_eq!(assembly.order(), "CABDFE");
         assert_eq!(assembly.work_time(2, 0), 15);
+
+        let mut steps = assembly.steps.clone();
+        steps.set_durations(|&step| step as u32 - 'A' as u32 + 1);
+        assert_eq!(
+            steps.schedule(2).unwrap().gantt(),
+            "Worker 0 |C--AB-D---E----|\n\
+             Worker 1 |...F-----......|\n"
+        );
+        assert_eq!(steps.critical_path().unwrap().tasks, vec!['C', 'F', 'E']);
     }
 
     #[test]
@@ -0,0 +1,351 @@
+//! Schedule a DAG of tasks across a fixed number of workers.
+//!
+//! Tasks can be any ordered type, each with its own duration. Whenever a worker is free it picks
+//! up the available task that comes first in the tie-break order (by default the tasks' own
+//! ordering), so the same inputs always give the same schedule.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+/// A set of tasks, their durations, and which tasks must finish before others can start.
+#[derive(Debug, Clone)]
+pub struct Scheduler<T: Ord> {
+    durations: BTreeMap<T, u32>,
+    /// Map from a task to the tasks that must be finished before it can start.
+    requirements: BTreeMap<T, BTreeSet<T>>,
+}
+
+/// One task being worked on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slot<T> {
+    pub worker: usize,
+    pub task: T,
+    pub start: u32,
+    pub end: u32,
+}
+
+/// Who did what, and when.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule<T> {
+    pub worker_count: usize,
+    /// Every task, in the order work on them started.
+    pub slots: Vec<Slot<T>>,
+}
+
+/// The chain of dependent tasks that takes longest, which bounds the schedule however many
+/// workers there are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CriticalPath<T> {
+    pub tasks: Vec<T>,
+    pub length: u32,
+}
+
+impl<T: Ord + Clone> Default for Scheduler<T> {
+    fn default() -> Self {
+        Scheduler {
+            durations: BTreeMap::new(),
+            requirements: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T: Ord + Clone> Scheduler<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a task, or change the duration of an existing one.
+    pub fn add_task(&mut self, task: T, duration: u32) {
+        self.requirements.entry(task.clone()).or_default();
+        self.durations.insert(task, duration);
+    }
+
+    /// Require `before` to be finished before `after` can start. Tasks that haven't been added
+    /// yet are added with a duration of zero.
+    pub fn add_dependency(&mut self, before: T, after: T) {
+        for task in &[&before, &after] {
+            if !self.durations.contains_key(*task) {
+                self.add_task(T::clone(task), 0);
+            }
+        }
+        self.requirements.get_mut(&after).unwrap().insert(before);
+    }
+
+    /// Set every task's duration from a function of the task.
+    pub fn set_durations<F: Fn(&T) -> u32>(&mut self, duration: F) {
+        for (task, d) in self.durations.iter_mut() {
+            *d = duration(task);
+        }
+    }
+
+    pub fn tasks(&self) -> impl Iterator<Item = &T> {
+        self.durations.keys()
+    }
+
+    /// Schedule the tasks, breaking ties between available tasks by their ordering.
+    ///
+    /// Panics if there are no workers.
+    pub fn schedule(&self, worker_count: usize) -> Result<Schedule<T>, Vec<T>> {
+        self.schedule_by(worker_count, |task| task.clone())
+    }
+
+    /// Schedule the tasks, handing free workers the available task with the lowest `priority`
+    /// (falling back to the tasks' ordering).
+    ///
+    /// Fails with the tasks that could never start if the dependencies have a cycle. Panics if
+    /// there are no workers, as then nothing could start either.
+    pub fn schedule_by<K, F>(&self, worker_count: usize, priority: F) -> Result<Schedule<T>, Vec<T>>
+    where
+        K: Ord,
+        F: Fn(&T) -> K,
+    {
+        assert!(worker_count > 0, "Can't schedule tasks without any workers");
+        let mut waiting_on: BTreeMap<&T, usize> = self
+            .requirements
+            .iter()
+            .map(|(task, reqs)| (task, reqs.len()))
+            .collect();
+        let dependents = self.dependents();
+        let mut available: Vec<&T> = self
+            .requirements
+            .iter()
+            .filter(|(_, reqs)| reqs.is_empty())
+            .map(|(task, _)| task)
+            .collect();
+        let mut running: Vec<Option<(&T, u32)>> = vec![None; worker_count];
+        let mut slots = Vec::with_capacity(self.durations.len());
+        let mut now = 0;
+
+        loop {
+            for worker in running.iter_mut() {
+                match *worker {
+                    Some((task, end)) if end <= now => {
+                        for &dependent in &dependents[task] {
+                            let count = waiting_on.get_mut(dependent).unwrap();
+                            *count -= 1;
+                            if *count == 0 {
+                                available.push(dependent);
+                            }
+                        }
+                        *worker = None;
+                    }
+                    _ => {}
+                }
+            }
+
+            available.sort_by(|a, b| priority(a).cmp(&priority(b)).then(a.cmp(b)));
+            let mut available_tasks = available.drain(..);
+            for (i, worker) in running.iter_mut().enumerate().filter(|(_, w)| w.is_none()) {
+                let task = match available_tasks.next() {
+                    Some(task) => task,
+                    None => break,
+                };
+                let end = now + self.durations[task];
+                slots.push(Slot {
+                    worker: i,
+                    task: task.clone(),
+                    start: now,
+                    end,
+                });
+                *worker = Some((task, end));
+            }
+            available = available_tasks.collect();
+
+            match running.iter().flatten().map(|&(_, end)| end).min() {
+                Some(end) => now = now.max(end),
+                None => break,
+            }
+        }
+
+        if slots.len() < self.durations.len() {
+            let started: BTreeSet<&T> = slots.iter().map(|slot| &slot.task).collect();
+            return Err(self
+                .tasks()
+                .filter(|task| !started.contains(task))
+                .cloned()
+                .collect());
+        }
+        Ok(Schedule {
+            worker_count,
+            slots,
+        })
+    }
+
+    /// The longest chain of dependent tasks, by total duration.
+    ///
+    /// Fails with the tasks that are part of (or wait on) a cycle.
+    pub fn critical_path(&self) -> Result<CriticalPath<T>, Vec<T>> {
+        // An unlimited number of workers finishes every task as early as possible.
+        let schedule = self.schedule(self.durations.len())?;
+        let finish: BTreeMap<&T, u32> = schedule
+            .slots
+            .iter()
+            .map(|slot| (&slot.task, slot.end))
+            .collect();
+
+        let mut last = match finish.iter().max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0))) {
+            Some((&task, _)) => task,
+            None => {
+                return Ok(CriticalPath {
+                    tasks: Vec::new(),
+                    length: 0,
+                })
+            }
+        };
+        let length = finish[last];
+        let mut tasks = vec![last.clone()];
+        // Walk back through whichever requirement held each task up the longest.
+        while let Some(previous) = self.requirements[last]
+            .iter()
+            .max_by(|a, b| finish[a].cmp(&finish[b]).then(b.cmp(a)))
+        {
+            tasks.push(previous.clone());
+            last = previous;
+        }
+        tasks.reverse();
+        Ok(CriticalPath { tasks, length })
+    }
+
+    /// Map from each task to the tasks that require it.
+    fn dependents(&self) -> BTreeMap<&T, Vec<&T>> {
+        let mut dependents: BTreeMap<&T, Vec<&T>> = self
+            .durations
+            .keys()
+            .map(|task| (task, Vec::new()))
+            .collect();
+        for (task, reqs) in &self.requirements {
+            for req in reqs {
+                dependents.get_mut(req).unwrap().push(task);
+            }
+        }
+        dependents
+    }
+}
+
+impl<T> Schedule<T> {
+    /// The tasks in the order work on them started.
+    pub fn order(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().map(|slot| &slot.task)
+    }
+
+    /// The time at which the last task finishes.
+    pub fn makespan(&self) -> u32 {
+        self.slots.iter().map(|slot| slot.end).max().unwrap_or(0)
+    }
+}
+
+impl<T: Display> Schedule<T> {
+    /// Draw the schedule as one row per worker, one column per time unit. Each task is drawn as
+    /// its name padded with `-` (or cut short) to the length of its slot, and idle time as `.`.
+    pub fn gantt(&self) -> String {
+        let width = self.makespan() as usize;
+        let mut rows = vec![vec!['.'; width]; self.worker_count];
+        for slot in &self.slots {
+            let name = slot.task.to_string();
+            let cells = name.chars().chain(std::iter::repeat('-'));
+            let row = &mut rows[slot.worker][slot.start as usize..slot.end as usize];
+            for (cell, c) in row.iter_mut().zip(cells) {
+                *cell = c;
+            }
+        }
+        let label_width = format!("Worker {}", self.worker_count.saturating_sub(1)).len();
+        rows.iter()
+            .enumerate()
+            .map(|(i, row)| {
+                format!(
+                    "{:<width$} |{}|\n",
+                    format!("Worker {}", i),
+                    row.iter().collect::<String>(),
+                    width = label_width
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small build pipeline with named tasks.
+    fn pipeline() -> Scheduler<&'static str> {
+        let mut scheduler = Scheduler::new();
+        scheduler.add_task("fetch", 2);
+        scheduler.add_task("lint", 1);
+        scheduler.add_task("build", 4);
+        scheduler.add_task("test", 3);
+        scheduler.add_task("docs", 2);
+        scheduler.add_task("release", 1);
+        scheduler.add_dependency("fetch", "lint");
+        scheduler.add_dependency("fetch", "build");
+        scheduler.add_dependency("fetch", "docs");
+        scheduler.add_dependency("build", "test");
+        scheduler.add_dependency("lint", "release");
+        scheduler.add_dependency("test", "release");
+        scheduler.add_dependency("docs", "release");
+        scheduler
+    }
+
+    #[test]
+    fn named_tasks() {
+        let schedule = pipeline().schedule(2).unwrap();
+        assert_eq!(
+            schedule.order().cloned().collect::<Vec<_>>(),
+            vec!["fetch", "build", "docs", "lint", "test", "release"]
+        );
+        assert_eq!(schedule.makespan(), 10);
+        assert_eq!(
+            schedule.slots[1],
+            Slot {
+                worker: 0,
+                task: "build",
+                start: 2,
+                end: 6
+            }
+        );
+        assert_eq!(
+            schedule.gantt(),
+            "Worker 0 |febuiltesr|\n\
+             Worker 1 |..dol.....|\n"
+        );
+    }
+
+    #[test]
+    fn tie_break_order() {
+        // Prefer the longest task first rather than alphabetical order.
+        let scheduler = pipeline();
+        let durations: BTreeMap<_, _> = scheduler.durations.clone();
+        let schedule = scheduler
+            .schedule_by(1, |task| std::cmp::Reverse(durations[task]))
+            .unwrap();
+        assert_eq!(
+            schedule.order().cloned().collect::<Vec<_>>(),
+            vec!["fetch", "build", "test", "docs", "lint", "release"]
+        );
+        assert_eq!(schedule.makespan(), 13);
+    }
+
+    #[test]
+    fn critical_path() {
+        assert_eq!(
+            pipeline().critical_path(),
+            Ok(CriticalPath {
+                tasks: vec!["fetch", "build", "test", "release"],
+                length: 10
+            })
+        );
+    }
+
+    #[test]
+    fn cycles_are_reported() {
+        let mut scheduler = pipeline();
+        scheduler.add_dependency("release", "build");
+        assert_eq!(scheduler.schedule(3), Err(vec!["build", "release", "test"]));
+    }
+
+    #[test]
+    #[should_panic(expected = "without any workers")]
+    fn no_workers() {
+        pipeline().schedule(0).unwrap();
+    }
+}
@@ -2,24 +2,36 @@
 #![doc(include = "../Question.md")]
 
 use std::collections::VecDeque;
-use std::mem;
+use std::fmt::Write;
+
+use failure::{ensure, format_err, Error};
 
 const INPUT: &str = include_str!("../input");
 
 pub fn answer() -> (String, String) {
-    let license_tree = LicenseTree::from(INPUT);
+    let license_tree = LicenseTree::from(INPUT).unwrap();
     (
         license_tree.metadata_sum().to_string(),
         license_tree.root_value().to_string(),
     )
 }
 
-#[derive(Debug, Clone)]
-struct LicenseTree {
+/// A license tree. Everything that walks the tree uses an explicit stack, so trees can be nested
+/// much more deeply than the call stack would allow.
+#[derive(Debug)]
+pub struct LicenseTree {
     root: LicenseNode,
 }
 
-#[derive(Debug, Clone, Default)]
+/// A step of a depth-first walk over the tree.
+enum Visit<'a> {
+    /// Entering a node, at this depth (the root is at depth zero).
+    Enter(&'a LicenseNode, usize),
+    /// Leaving a node, after visiting all its children.
+    Exit(&'a LicenseNode),
+}
+
+#[derive(Debug)]
 struct LicenseNode {
     child_count: u32,
     metadata_count: u32,
@@ -27,27 +39,6 @@ struct LicenseNode {
     metadata: VecDeque<u32>,
 }
 
-impl LicenseTree {
-    fn from(s: &str) -> Self {
-        let mut input: VecDeque<u32> = s
-            .split_whitespace()
-            .map(|w| w.parse::<u32>().unwrap())
-            .collect();
-
-        LicenseTree {
-            root: LicenseNode::from(&mut input),
-        }
-    }
-
-    fn metadata_sum(&self) -> u32 {
-        self.root.metadata_sum()
-    }
-
-    fn root_value(&self) -> u32 {
-        self.root.value()
-    }
-}
-
 /// 2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2 <- All
 /// 2 3                           1 1 2 <- A
 ///     0 3 10 11 12 1 1 0 1 99 2       <- A Children
@@ -55,67 +46,227 @@ impl LicenseTree {
 ///                  1 1        2       <- C
 ///                      0 1 99         <- C children
 ///                      0 1 99         <- D
-impl LicenseNode {
-    fn from(input: &mut VecDeque<u32>) -> Self {
-        let child_count = input.pop_front().unwrap();
-        let metadata_count = input.pop_front().unwrap();
+impl LicenseTree {
+    pub fn from(s: &str) -> Result<Self, Error> {
+        let mut input = s.split_whitespace().map(|w| {
+            w.parse::<u32>()
+                .map_err(|e| format_err!("Invalid number {:?}: {}", w, e))
+        });
+        let mut next = || -> Result<u32, Error> {
+            input
+                .next()
+                .unwrap_or_else(|| Err(format_err!("Input ended in the middle of a node")))
+        };
 
-        let mut children = VecDeque::new();
+        // The nodes we're inside, each with the children parsed so far.
+        let mut stack = vec![LicenseNode::header(&mut next)?];
+        let root = loop {
+            let node = stack.last_mut().unwrap();
+            if (node.children.len() as u32) < node.child_count {
+                stack.push(LicenseNode::header(&mut next)?);
+                continue;
+            }
+            for _ in 0..node.metadata_count {
+                node.metadata.push_back(next()?);
+            }
+            let node = stack.pop().unwrap();
+            match stack.last_mut() {
+                Some(parent) => parent.children.push_back(node),
+                None => break node,
+            }
+        };
+        ensure!(
+            input.next().is_none(),
+            "Input continues after the end of the root node"
+        );
+        Ok(LicenseTree { root })
+    }
 
-        let mut children_left = child_count;
-        while children_left > 0 {
-            children.push_back(Self::from(input));
-            children_left -= 1;
-        }
+    pub fn metadata_sum(&self) -> u32 {
+        let mut sum = 0;
+        self.walk(|visit| {
+            if let Visit::Enter(node, _) = visit {
+                sum += node.metadata.iter().sum::<u32>();
+            }
+        });
+        sum
+    }
 
-        let remaining = input.split_off(metadata_count as usize);
-        let metadata = mem::replace(input, remaining);
+    pub fn root_value(&self) -> u32 {
+        // The values of the nodes whose parents haven't been finished yet.
+        let mut values: Vec<u32> = Vec::new();
+        self.walk(|visit| {
+            if let Visit::Exit(node) = visit {
+                let children = values.split_off(values.len() - node.children.len());
+                let value = if node.children.is_empty() {
+                    node.metadata.iter().sum()
+                } else {
+                    node.metadata
+                        .iter()
+                        .filter_map(|&n| children.get((n as usize).checked_sub(1)?))
+                        .sum()
+                };
+                values.push(value);
+            }
+        });
+        values[0]
+    }
+
+    /// The tree in the puzzle's format: each node's child count and metadata count, then its
+    /// children, then its metadata, all separated by spaces.
+    pub fn encode(&self) -> String {
+        let mut numbers = Vec::new();
+        self.walk(|visit| match visit {
+            Visit::Enter(node, _) => {
+                numbers.push(node.child_count);
+                numbers.push(node.metadata_count);
+            }
+            Visit::Exit(node) => numbers.extend(&node.metadata),
+        });
+        let numbers: Vec<String> = numbers.iter().map(u32::to_string).collect();
+        numbers.join(" ")
+    }
+
+    /// The tree with one node per line, indented by depth, showing each node's metadata.
+    ///
+    /// ```text
+    /// * 1 1 2
+    ///   * 10 11 12
+    ///   * 2
+    ///     * 99
+    /// ```
+    pub fn pretty(&self) -> String {
+        let mut out = String::new();
+        self.walk(|visit| {
+            if let Visit::Enter(node, depth) = visit {
+                write!(out, "{:indent$}*", "", indent = depth * 2).unwrap();
+                for entry in &node.metadata {
+                    write!(out, " {}", entry).unwrap();
+                }
+                out.push('\n');
+            }
+        });
+        out
+    }
 
-        LicenseNode {
-            child_count,
-            metadata_count,
-            metadata,
-            children,
+    /// Visit every node depth first, entering it before its children and exiting after.
+    fn walk<'a, F: FnMut(Visit<'a>)>(&'a self, mut visit: F) {
+        // Each node we're inside, and the index of the next child to visit.
+        let mut stack = vec![(&self.root, 0)];
+        visit(Visit::Enter(&self.root, 0));
+        while let Some(&mut (node, ref mut next_child)) = stack.last_mut() {
+            match node.children.get(*next_child) {
+                Some(child) => {
+                    *next_child += 1;
+                    stack.push((child, 0));
+                    visit(Visit::Enter(child, stack.len() - 1));
+                }
+                None => {
+                    stack.pop();
+                    visit(Visit::Exit(node));
+                }
+            }
         }
     }
+}
 
-    fn metadata_sum(&self) -> u32 {
-        self.metadata.iter().sum::<u32>()
-            + self.children.iter().map(Self::metadata_sum).sum::<u32>()
+impl LicenseNode {
+    /// A node with no children or metadata yet, from the next two numbers of the input.
+    fn header<F: FnMut() -> Result<u32, Error>>(next: &mut F) -> Result<Self, Error> {
+        Ok(LicenseNode {
+            child_count: next()?,
+            metadata_count: next()?,
+            children: VecDeque::new(),
+            metadata: VecDeque::new(),
+        })
     }
+}
 
-    fn value(&self) -> u32 {
-        assert_eq!(self.child_count as usize, self.children.len());
-        if self.child_count == 0 {
-            self.metadata.iter().sum()
-        } else {
-            self.metadata
-                .iter()
-                .map(|&n| {
-                    if n == 0 {
-                        0
-                    } else if let Some(child) = self.children.get(n as usize - 1) {
-                        child.value()
-                    } else {
-                        0
-                    }
-                })
-                .sum()
+impl Drop for LicenseNode {
+    /// Drop the descendants one at a time, rather than recursively.
+    fn drop(&mut self) {
+        let mut stack: Vec<LicenseNode> = self.children.drain(..).collect();
+        while let Some(mut node) = stack.pop() {
+            stack.extend(node.children.drain(..));
         }
     }
 }
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const EXAMPLE: &str = "2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2";
+
+    /// A tree `depth` nodes deep, where each node has one child and one metadata entry, `1`.
+    fn deep_tree(depth: usize) -> String {
+        let mut input = "1 1 ".repeat(depth - 1);
+        input.push_str("0 1");
+        input.push_str(&" 1".repeat(depth));
+        input
+    }
+
     #[test]
     fn example() {
-        let input = "2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2";
-        let license_tree = LicenseTree::from(input);
+        let license_tree = LicenseTree::from(EXAMPLE).unwrap();
         assert_eq!(license_tree.metadata_sum(), 138);
         assert_eq!(license_tree.root_value(), 66);
     }
 
+    #[test]
+    fn round_trip() {
+        for input in &[
+            EXAMPLE,
+            "0 0",
+            "0 2 5 6",
+            "3 0 0 1 7 0 0 1 1 0 1 2 3",
+            &deep_tree(5),
+        ] {
+            assert_eq!(&LicenseTree::from(input).unwrap().encode(), input);
+        }
+        let input = "  2 3\n0 3 10 11 12 1 1 0 1 99 2 1 1 2\n";
+        assert_eq!(LicenseTree::from(input).unwrap().encode(), EXAMPLE);
+    }
+
+    #[test]
+    fn pretty() {
+        assert_eq!(
+            LicenseTree::from(EXAMPLE).unwrap().pretty(),
+            "* 1 1 2\n  \
+             * 10 11 12\n  \
+             * 2\n    \
+             * 99\n"
+        );
+        assert_eq!(LicenseTree::from("1 0 0 0").unwrap().pretty(), "*\n  *\n");
+    }
+
+    #[test]
+    fn deep_trees() {
+        let depth = 1_000_000;
+        let input = deep_tree(depth);
+        let license_tree = LicenseTree::from(&input).unwrap();
+        assert_eq!(license_tree.metadata_sum(), depth as u32);
+        assert_eq!(license_tree.root_value(), 1);
+        assert_eq!(license_tree.encode(), input);
+
+        let pretty = LicenseTree::from(&deep_tree(1000)).unwrap().pretty();
+        assert_eq!(
+            pretty.lines().last(),
+            Some(&*format!("{}* 1", " ".repeat(2 * 999)))
+        );
+    }
+
+    #[test]
+    fn invalid_input() {
+        assert!(LicenseTree::from("").is_err());
+        assert!(LicenseTree::from("1 1 0 1 2").is_err());
+        assert!(LicenseTree::from("0 1 2 3").is_err());
+        assert!(LicenseTree::from("0 1 x").is_err());
+        assert!(LicenseTree::from("0 0 x").is_err());
+        assert!(LicenseTree::from("0 0 1 x").is_err());
+        assert!(LicenseTree::from("0 1 1 x").is_err());
+    }
+
     #[test]
     fn test_answer() {
         assert_eq!(answer(), (String::from("43996"), String::from("35189")));
@@ -1,7 +1,7 @@
 #![feature(external_doc)]
 #![doc(include = "../Question.md")]
 
-use std::collections::VecDeque;
+use circular_list::CircularList;
 
 const INPUT: &str = include_str!("../input");
 
@@ -33,23 +33,17 @@ impl Game {
     }
 
     fn high_score(&self) -> usize {
-        let mut board = VecDeque::with_capacity(self.last_marble);
-        board.push_front(0);
+        let mut board = CircularList::with_capacity(self.last_marble + 1);
+        board.insert_after(0);
         let mut scores = vec![0; self.player_count];
 
         for marble in 1..=self.last_marble {
             if marble % 23 == 0 {
-                for _ in 0..7 {
-                    let m = board.pop_back().unwrap();
-                    board.push_front(m);
-                }
-                scores[marble % self.player_count] += marble + board.pop_front().unwrap();
+                board.rotate(-7);
+                scores[marble % self.player_count] += marble + board.remove().unwrap();
             } else {
-                for _ in 0..2 {
-                    let m = board.pop_front().unwrap();
-                    board.push_back(m);
-                }
-                board.push_front(marble);
+                board.rotate(1);
+                board.insert_after(marble);
             }
         }
         *scores.iter().max().unwrap()
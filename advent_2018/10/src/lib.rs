@@ -4,11 +4,14 @@
 
 extern crate test;
 
+mod ocr;
+
 use failure::{bail, format_err, Error};
 use lazy_static::lazy_static;
 use regex::Regex;
 
 use std::collections::HashSet;
+use std::convert::TryFrom;
 use std::fmt;
 use std::i32;
 use std::str::FromStr;
@@ -17,7 +20,7 @@ const INPUT: &str = include_str!("../input");
 
 pub fn answer() -> (String, String) {
     let (coords, time) = Sky::from(INPUT).unwrap().message().unwrap();
-    (coords.to_string(), time.to_string())
+    (ocr::read(&coords.to_string()).unwrap(), time.to_string())
 }
 
 #[derive(Debug, Clone)]
@@ -91,28 +94,83 @@ impl Sky {
         })
     }
 
-    fn inc_time(&mut self) {
-        self.time += 1;
-        self.coords.map.clear();
-        for (p, v) in self.coords.list.iter_mut().zip(self.points.iter()) {
-            std::mem::replace(p, (p.0 + v.velocity.0, p.1 + v.velocity.1));
-            self.coords.map.insert(*p);
+    /// The position of every point at time `t`, which may be too far away to fit in an `i32`.
+    fn positions_at(&self, t: i64) -> impl Iterator<Item = (i128, i128)> + '_ {
+        let t = i128::from(t);
+        self.points.iter().map(move |p| {
+            (
+                i128::from(p.position.0) + i128::from(p.velocity.0) * t,
+                i128::from(p.position.1) + i128::from(p.velocity.1) * t,
+            )
+        })
+    }
+
+    /// The width and height of the smallest rectangle containing every point at time `t`.
+    fn size_at(&self, t: i64) -> (i128, i128) {
+        let mut max = (i128::MIN, i128::MIN);
+        let mut min = (i128::MAX, i128::MAX);
+        for (x, y) in self.positions_at(t) {
+            max = (max.0.max(x), max.1.max(y));
+            min = (min.0.min(x), min.1.min(y));
         }
+        (max.0 - min.0 + 1, max.1 - min.1 + 1)
     }
 
     /// Return the message in the sky (as a coordinate list, convert to string to show the
     /// message) and the time at which it was generated.
+    ///
+    /// The message appears when the points are closest together, i.e. when the area of their
+    /// bounding box is smallest. The points move in straight lines, so the box's width shrinks
+    /// until some time and grows afterwards, and so does its height. Before both of those times
+    /// the area is shrinking and after both it's growing, so only the times in between need
+    /// checking. (The area itself can go down, up and down again between them.)
     fn message(&mut self) -> Result<(Coords, i32), Error> {
-        while self.time < i32::MAX {
-            self.inc_time();
-            if self.coords.all_touching() {
-                return Ok((self.coords.clone(), self.time));
-            }
+        if self.points.is_empty() {
+            bail!("There are no points in the sky.");
         }
-        Err(format_err!("Didn't find a point where they all touch."))
+
+        let narrowest = lowest_point(|t| self.size_at(t).0);
+        let shortest = lowest_point(|t| self.size_at(t).1);
+        let time = (narrowest.min(shortest)..=narrowest.max(shortest))
+            .min_by_key(|&t| {
+                let (width, height) = self.size_at(t);
+                width * height
+            })
+            .unwrap();
+
+        let list = self
+            .positions_at(time)
+            .map(|(x, y)| Some((i32::try_from(x).ok()?, i32::try_from(y).ok()?)))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| format_err!("The message at time {} is off the chart", time))?;
+        self.time = i32::try_from(time)?;
+        self.coords = Coords::from(list);
+        Ok((self.coords.clone(), self.time))
     }
 }
 
+/// A time `t >= 0` at which `f` is smallest, given that `f` only goes down then up (with
+/// perhaps a level stretch at the bottom). Doubles the time until `f` stops going down, then
+/// ternary searches the last interval, so it only takes a few dozen calls.
+fn lowest_point<F: Fn(i64) -> i128>(f: F) -> i64 {
+    let (mut low, mut high) = (0, 1);
+    while f(high * 2) < f(high) {
+        low = high;
+        high *= 2;
+    }
+    high *= 2;
+
+    while high - low > 2 {
+        let third = (high - low) / 3;
+        if f(low + third) <= f(high - third) {
+            high -= third;
+        } else {
+            low += third;
+        }
+    }
+    (low..=high).min_by_key(|&t| f(t)).unwrap()
+}
+
 impl Coords {
     fn from(v: Vec<(i32, i32)>) -> Self {
         let mut coord_map = HashSet::new();
@@ -124,19 +182,6 @@ impl Coords {
             map: coord_map,
         }
     }
-
-    fn all_touching(&self) -> bool {
-        self.list.iter().all(|&(x, y)| {
-            self.map.contains(&(x, y + 1))
-                || self.map.contains(&(x, y - 1))
-                || self.map.contains(&(x + 1, y))
-                || self.map.contains(&(x - 1, y))
-                || self.map.contains(&(x - 1, y - 1))
-                || self.map.contains(&(x - 1, y + 1))
-                || self.map.contains(&(x + 1, y - 1))
-                || self.map.contains(&(x + 1, y + 1))
-        })
-    }
 }
 
 impl fmt::Display for Coords {
@@ -234,21 +279,48 @@ mod tests {
         assert_eq!(time, 3);
     }
 
+    #[test]
+    fn isolated_points() {
+        // An extra point that ends up two columns from the top left of the H, with no neighbours.
+        let input = format!("{}position=< 8,  6> velocity=<-2, -2>\n", EXAMPLE_INPUT);
+        let output = "#.#.#..###\n\
+                      #...#...#.\n\
+                      #...#...#.\n\
+                      #####...#.\n\
+                      #...#...#.\n\
+                      #...#...#.\n\
+                      #...#...#.\n\
+                      #...#..###\n";
+
+        let (coords, time) = Sky::from(&input).unwrap().message().unwrap();
+        assert_eq!(coords.to_string(), output);
+        assert_eq!(time, 3);
+    }
+
+    #[test]
+    fn two_dips() {
+        // The width is t + 2 and the height |100 - t| + 1, so the area is 202 at t = 0, goes up,
+        // and comes down to 102 at t = 100.
+        let input = "position=< 0,   0> velocity=< 0,  0>\n\
+                     position=< 1, 100> velocity=< 1, -1>\n";
+        let (coords, time) = Sky::from(input).unwrap().message().unwrap();
+        assert_eq!(time, 100);
+        assert_eq!(coords.list, vec![(0, 0), (101, 0)]);
+    }
+
+    #[test]
+    fn fast_points() {
+        // The points only meet after the positions at the doubled times overflow an i32.
+        let input = "position=< 2000000000, 0> velocity=<-2000000000, 0>\n\
+                     position=<-2000000000, 0> velocity=< 2000000000, 0>\n";
+        let (coords, time) = Sky::from(input).unwrap().message().unwrap();
+        assert_eq!(time, 1);
+        assert_eq!(coords.list, vec![(0, 0), (0, 0)]);
+    }
+
     #[test]
     fn test_answer() {
-        // GPJLLLLH
-        let output = ".####...#####......###..#.......#.......#.......#.......#....#\n\
-                      #....#..#....#......#...#.......#.......#.......#.......#....#\n\
-                      #.......#....#......#...#.......#.......#.......#.......#....#\n\
-                      #.......#....#......#...#.......#.......#.......#.......#....#\n\
-                      #.......#####.......#...#.......#.......#.......#.......######\n\
-                      #..###..#...........#...#.......#.......#.......#.......#....#\n\
-                      #....#..#...........#...#.......#.......#.......#.......#....#\n\
-                      #....#..#.......#...#...#.......#.......#.......#.......#....#\n\
-                      #...##..#.......#...#...#.......#.......#.......#.......#....#\n\
-                      .###.#..#........###....######..######..######..######..#....#\n";
-
-        assert_eq!(answer(), (String::from(output), 10515.to_string()));
+        assert_eq!(answer(), (String::from("GPJLLLLH"), 10515.to_string()));
     }
 
     #[bench]
@@ -260,5 +332,4 @@ mod tests {
     fn bench_answer(b: &mut Bencher) {
         b.iter(|| Sky::from(INPUT).unwrap().message().unwrap())
     }
-
 }
@@ -0,0 +1,240 @@
+//! Read the capital letters that puzzle answers are drawn in.
+//!
+//! Advent of Code uses two fonts: a small one six pixels high and a large one ten pixels high.
+//! Only the letters that have appeared in puzzles are known.
+
+use failure::{bail, format_err, Error};
+
+const SMALL_HEIGHT: usize = 6;
+const LARGE_HEIGHT: usize = 10;
+
+const SMALL_FONT: &[(char, &str)] = &[
+    ('A', ".##.\n#..#\n#..#\n####\n#..#\n#..#"),
+    ('B', "###.\n#..#\n###.\n#..#\n#..#\n###."),
+    ('C', ".##.\n#..#\n#...\n#...\n#..#\n.##."),
+    ('E', "####\n#...\n###.\n#...\n#...\n####"),
+    ('F', "####\n#...\n###.\n#...\n#...\n#..."),
+    ('G', ".##.\n#..#\n#...\n#.##\n#..#\n.###"),
+    ('H', "#..#\n#..#\n####\n#..#\n#..#\n#..#"),
+    ('I', "###\n.#.\n.#.\n.#.\n.#.\n###"),
+    ('J', "..##\n...#\n...#\n...#\n#..#\n.##."),
+    ('K', "#..#\n#.#.\n##..\n#.#.\n#.#.\n#..#"),
+    ('L', "#...\n#...\n#...\n#...\n#...\n####"),
+    ('O', ".##.\n#..#\n#..#\n#..#\n#..#\n.##."),
+    ('P', "###.\n#..#\n#..#\n###.\n#...\n#..."),
+    ('R', "###.\n#..#\n#..#\n###.\n#.#.\n#..#"),
+    ('S', ".###\n#...\n#...\n.##.\n...#\n###."),
+    ('U', "#..#\n#..#\n#..#\n#..#\n#..#\n.##."),
+    ('Y', "#...#\n#...#\n.#.#.\n..#..\n..#..\n..#.."),
+    ('Z', "####\n...#\n..#.\n.#..\n#...\n####"),
+];
+
+const LARGE_FONT: &[(char, &str)] = &[
+    (
+        'A',
+        "..##..\n.#..#.\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#",
+    ),
+    (
+        'B',
+        "#####.\n#....#\n#....#\n#....#\n#####.\n#....#\n#....#\n#....#\n#....#\n#####.",
+    ),
+    (
+        'C',
+        ".####.\n#....#\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#....#\n.####.",
+    ),
+    (
+        'E',
+        "######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n######",
+    ),
+    (
+        'F',
+        "######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n#.....",
+    ),
+    (
+        'G',
+        ".####.\n#....#\n#.....\n#.....\n#.....\n#..###\n#....#\n#....#\n#...##\n.###.#",
+    ),
+    (
+        'H',
+        "#....#\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#\n#....#",
+    ),
+    (
+        'J',
+        "...###\n....#.\n....#.\n....#.\n....#.\n....#.\n....#.\n#...#.\n#...#.\n.###..",
+    ),
+    (
+        'K',
+        "#....#\n#...#.\n#..#..\n#.#...\n##....\n##....\n#.#...\n#..#..\n#...#.\n#....#",
+    ),
+    (
+        'L',
+        "#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n######",
+    ),
+    (
+        'N',
+        "#....#\n##...#\n##...#\n#.#..#\n#.#..#\n#..#.#\n#..#.#\n#...##\n#...##\n#....#",
+    ),
+    (
+        'P',
+        "#####.\n#....#\n#....#\n#....#\n#####.\n#.....\n#.....\n#.....\n#.....\n#.....",
+    ),
+    (
+        'R',
+        "#####.\n#....#\n#....#\n#....#\n#####.\n#..#..\n#...#.\n#...#.\n#....#\n#....#",
+    ),
+    (
+        'X',
+        "#....#\n#....#\n.#..#.\n.#..#.\n..##..\n..##..\n.#..#.\n.#..#.\n#....#\n#....#",
+    ),
+    (
+        'Z',
+        "######\n.....#\n.....#\n....#.\n...#..\n..#...\n.#....\n#.....\n#.....\n######",
+    ),
+];
+
+/// A glyph as rows of lit (`true`) and unlit pixels.
+type Glyph = Vec<Vec<bool>>;
+
+/// Read the letters in a grid drawn with `#` for lit pixels and anything else (usually `.`) for
+/// unlit ones. Letters are separated by at least one empty column, and blank rows and columns
+/// around the text are ignored.
+pub fn read(grid: &str) -> Result<String, Error> {
+    let pixels = trim(parse(grid));
+    let font = match pixels.len() {
+        SMALL_HEIGHT => SMALL_FONT,
+        LARGE_HEIGHT => LARGE_FONT,
+        0 => return Ok(String::new()),
+        height => bail!("No font is {} pixels high:\n{}", height, grid),
+    };
+
+    split(&pixels)
+        .into_iter()
+        .map(|glyph| {
+            let glyph = trim(glyph);
+            font.iter()
+                .find(|(_, pattern)| trim(parse(pattern)) == glyph)
+                .map(|&(letter, _)| letter)
+                .ok_or_else(|| format_err!("Unknown letter:\n{}", draw(&glyph)))
+        })
+        .collect()
+}
+
+fn parse(grid: &str) -> Glyph {
+    grid.lines()
+        .map(|line| line.chars().map(|c| c == '#').collect())
+        .collect()
+}
+
+fn draw(glyph: &[Vec<bool>]) -> String {
+    glyph
+        .iter()
+        .map(|row| {
+            let mut line: String = row.iter().map(|&lit| if lit { '#' } else { '.' }).collect();
+            line.push('\n');
+            line
+        })
+        .collect()
+}
+
+/// Whether any pixel in column `x` is lit.
+fn column_lit(pixels: &[Vec<bool>], x: usize) -> bool {
+    pixels.iter().any(|row| row.get(x) == Some(&true))
+}
+
+/// Remove the unlit rows and columns around the lit pixels, padding short rows so that every
+/// row is the same width.
+fn trim(pixels: Glyph) -> Glyph {
+    let rows: Vec<&Vec<bool>> = pixels
+        .iter()
+        .skip_while(|row| !row.contains(&true))
+        .collect();
+    let height = rows.len()
+        - rows
+            .iter()
+            .rev()
+            .take_while(|row| !row.contains(&true))
+            .count();
+    let rows = &rows[..height];
+
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let lit: Vec<usize> = (0..width)
+        .filter(|&x| rows.iter().any(|row| row.get(x) == Some(&true)))
+        .collect();
+    let (left, right) = match (lit.first(), lit.last()) {
+        (Some(&left), Some(&right)) => (left, right),
+        _ => return Vec::new(),
+    };
+    rows.iter()
+        .map(|row| (left..=right).map(|x| row.get(x) == Some(&true)).collect())
+        .collect()
+}
+
+/// Split trimmed pixels into glyphs at each run of empty columns.
+fn split(pixels: &[Vec<bool>]) -> Vec<Glyph> {
+    let width = pixels.iter().map(|row| row.len()).max().unwrap_or(0);
+    let mut glyphs = Vec::new();
+    let mut start = None;
+    for x in 0..=width {
+        match (x < width && column_lit(pixels, x), start) {
+            (true, None) => start = Some(x),
+            (false, Some(from)) => {
+                glyphs.push(
+                    pixels
+                        .iter()
+                        .map(|row| (from..x).map(|x| row.get(x) == Some(&true)).collect())
+                        .collect(),
+                );
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    glyphs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_font() {
+        let grid = "\
+                    .##..###..#..#.####.\n\
+                    #..#.#..#.#..#....#.\n\
+                    #..#.###..#..#...#..\n\
+                    ####.#..#.#..#..#...\n\
+                    #..#.#..#.#..#.#....\n\
+                    #..#.###...##..####.\n";
+        assert_eq!(read(grid).unwrap(), "ABUZ");
+    }
+
+    #[test]
+    fn large_font_with_border() {
+        let grid = "\
+                    ..........\n\
+                    .#....#...\n\
+                    .#....#...\n\
+                    ..#..#....\n\
+                    ..#..#....\n\
+                    ...##.....\n\
+                    ...##.....\n\
+                    ..#..#....\n\
+                    ..#..#....\n\
+                    .#....#...\n\
+                    .#....#...\n\
+                    ..........\n";
+        assert_eq!(read(grid).unwrap(), "X");
+    }
+
+    #[test]
+    fn unknown_letters() {
+        // The example from the puzzle is drawn in a font eight pixels high.
+        assert!(read("#...#..###\n#####...#.\n").is_err());
+        let grid = "#####\n#...#\n#...#\n#...#\n#...#\n#####\n";
+        assert_eq!(
+            read(grid).unwrap_err().to_string(),
+            format!("Unknown letter:\n{}", grid)
+        );
+        assert_eq!(read("......\n").unwrap(), "");
+    }
+}
@@ -1,15 +1,18 @@
 #![feature(external_doc)]
 #![doc(include = "../Question.md")]
 
-// Looks like you're supposed to use this: https://en.wikipedia.org/wiki/Summed-area_table
+pub mod summed_area;
 
 use failure::Error;
 use rayon::prelude::*;
 
-use std::i32;
+use crate::summed_area::SummedAreaTable;
 
 const INPUT: &str = include_str!("../input");
 
+/// The fuel cell grid is 300x300, with coordinates starting at 1.
+const GRID_SIZE: usize = 300;
+
 pub fn answer() -> (String, String) {
     let grid = Grid::from(INPUT).unwrap();
     let answer_1 = grid.total_power(3).0;
@@ -22,55 +25,60 @@ pub fn answer() -> (String, String) {
 
 #[derive(Debug)]
 struct Grid {
-    points: Vec<Vec<i32>>,
-    serial_number: i32,
+    table: SummedAreaTable,
 }
 
 impl Grid {
     fn from(s: &str) -> Result<Self, Error> {
         let serial_number = s.trim_end().parse::<i32>()?;
-        let points: Vec<Vec<i32>> = (0..301)
-            .into_par_iter()
-            .map(|y| {
-                (0..301)
-                    .into_par_iter()
-                    .map(|x| power_level((x, y), serial_number))
-                    .collect()
-            })
-            .collect();
-        Ok(Grid {
-            points,
-            serial_number,
-        })
+        Ok(Grid::new(GRID_SIZE, GRID_SIZE, |point| {
+            power_level(point, serial_number)
+        }))
+    }
+
+    /// A `width` x `height` grid with the top left cell at `(1, 1)`, where each cell's power is
+    /// `power(x, y)`.
+    fn new<F: Fn((i32, i32)) -> i32>(width: usize, height: usize, power: F) -> Self {
+        let table = SummedAreaTable::new(width, height, |x, y| {
+            i64::from(power((x as i32 + 1, y as i32 + 1)))
+        });
+        Grid { table }
     }
 
-    fn largest_total_power(&self) -> ((usize, usize), i32, usize) {
-        (0_usize..300_usize)
+    /// The square of any size with the largest total power, as (top left, power, size).
+    fn largest_total_power(&self) -> ((usize, usize), i64, usize) {
+        (1..self.table.width().min(self.table.height()) + 1)
             .into_par_iter()
             .map(|n| {
-                let p = self.total_power(n);
-                (((p.0).0, (p.0).1), p.1, n)
+                let (point, power) = self.total_power(n);
+                (point, power, n)
             })
-            .max_by(|x, y| x.1.cmp(&y.1))
+            .max_by_key(|&(_, power, n)| (power, std::cmp::Reverse(n)))
             .unwrap()
     }
 
-    fn total_power(&self, n: usize) -> ((usize, usize), i32) {
-        (0..(300 - n))
-            .flat_map(|y| (0..(300 - n)).map(move |x| (x, y)))
-            .fold(((0, 0), 0), |acc, (x, y)| {
-                let mut total_power = 0_i32;
-                for j in 0..n {
-                    for i in 0..n {
-                        total_power += self.points[y + j][x + i];
-                    }
-                }
-
-                if total_power >= acc.1 {
-                    ((x, y), total_power)
-                } else {
-                    acc
-                }
+    /// The top left corner and total power of the `n` x `n` square with the most power.
+    ///
+    /// Panics if the square is bigger than the grid.
+    fn total_power(&self, n: usize) -> ((usize, usize), i64) {
+        self.best_rectangle(n, n)
+            .unwrap_or_else(|| panic!("{}x{} square doesn't fit in the grid", n, n))
+    }
+
+    /// The top left corner and total power of the `w` x `h` rectangle with the most power, or
+    /// `None` if the rectangle doesn't fit in the grid. Ties go to the first rectangle in
+    /// reading order.
+    fn best_rectangle(&self, w: usize, h: usize) -> Option<((usize, usize), i64)> {
+        let (width, height) = (self.table.width(), self.table.height());
+        if w > width || h > height {
+            return None;
+        }
+        (0..=height - h)
+            .flat_map(|y| (0..=width - w).map(move |x| (x, y)))
+            .map(|(x, y)| ((x + 1, y + 1), self.table.sum((x, y), w, h)))
+            .fold(None, |best, (point, power)| match best {
+                Some((_, best_power)) if best_power >= power => best,
+                _ => Some((point, power)),
             })
     }
 }
@@ -106,6 +114,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn rectangles() {
+        // Power increases to the right and decreases downwards.
+        let grid = Grid::new(4, 3, |(x, y)| x - 2 * y);
+        assert_eq!(grid.best_rectangle(1, 1), Some(((4, 1), 2)));
+        assert_eq!(grid.best_rectangle(4, 1), Some(((1, 1), 2)));
+        assert_eq!(grid.best_rectangle(2, 3), Some(((3, 1), -3)));
+        assert_eq!(grid.best_rectangle(5, 1), None);
+        assert_eq!(grid.largest_total_power(), ((4, 1), 2, 1));
+    }
+
     #[test]
     fn second_example() {
         assert_eq!(
@@ -0,0 +1,83 @@
+//! A [summed-area table](https://en.wikipedia.org/wiki/Summed-area_table): after one pass over a
+//! grid, the sum of any rectangle in it takes four lookups.
+
+/// Prefix sums of a `width` x `height` grid of values, indexed from `(0, 0)` at the top left.
+#[derive(Debug, Clone)]
+pub struct SummedAreaTable {
+    width: usize,
+    height: usize,
+    /// `sums[y * (width + 1) + x]` is the sum of every value above and to the left of `(x, y)`,
+    /// so the first row and column are all zero.
+    sums: Vec<i64>,
+}
+
+impl SummedAreaTable {
+    /// Build the table from the value at each `(x, y)`.
+    pub fn new<F: Fn(usize, usize) -> i64>(width: usize, height: usize, value: F) -> Self {
+        let stride = width + 1;
+        let mut sums = vec![0; stride * (height + 1)];
+        for y in 0..height {
+            let mut row_sum = 0;
+            for x in 0..width {
+                row_sum += value(x, y);
+                sums[(y + 1) * stride + x + 1] = sums[y * stride + x + 1] + row_sum;
+            }
+        }
+        SummedAreaTable {
+            width,
+            height,
+            sums,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The sum of the `w` x `h` rectangle with its top left corner at `(x, y)`.
+    ///
+    /// Panics if the rectangle doesn't fit in the grid.
+    pub fn sum(&self, (x, y): (usize, usize), w: usize, h: usize) -> i64 {
+        assert!(
+            x + w <= self.width && y + h <= self.height,
+            "{}x{} rectangle at {:?} is outside the {}x{} grid",
+            w,
+            h,
+            (x, y),
+            self.width,
+            self.height
+        );
+        let at = |x: usize, y: usize| self.sums[y * (self.width + 1) + x];
+        at(x + w, y + h) - at(x, y + h) - at(x + w, y) + at(x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rectangle_sums() {
+        let values = [[1, 2, 3], [4, 5, 6]];
+        let table = SummedAreaTable::new(3, 2, |x, y| values[y][x]);
+        assert_eq!(table.sum((0, 0), 3, 2), 21);
+        assert_eq!(table.sum((1, 0), 2, 2), 16);
+        assert_eq!(table.sum((2, 1), 1, 1), 6);
+        assert_eq!(table.sum((1, 1), 0, 1), 0);
+        for (y, row) in values.iter().enumerate() {
+            for (x, &value) in row.iter().enumerate() {
+                assert_eq!(table.sum((x, y), 1, 1), value);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn rectangle_outside_grid() {
+        SummedAreaTable::new(3, 2, |_, _| 1).sum((1, 1), 3, 1);
+    }
+}
@@ -0,0 +1,282 @@
+//! A one-dimensional cellular automaton on an infinite row of cells.
+//!
+//! Rows are stored as bits packed into `u64` words, trimmed so that the first and last stored
+//! cells are alive. Each cell's next state is looked up in a table indexed by its neighbourhood
+//! read as a binary number (leftmost cell first), so a five-cell neighbourhood has a 32-entry
+//! table.
+
+use std::collections::HashMap;
+
+use failure::{ensure, Error};
+
+/// The widest neighbourhood supported, which keeps the lookup table to a few megabytes.
+const MAX_WIDTH: usize = 21;
+
+/// A row of cells, of which only finitely many are alive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    /// The index of the first live cell (zero if there are none).
+    offset: i64,
+    /// The number of cells stored, from the first live cell to the last one.
+    len: usize,
+    words: Vec<u64>,
+}
+
+/// Builds a trimmed `Row` from cells pushed in order.
+#[derive(Debug, Default)]
+struct RowBuilder {
+    offset: Option<i64>,
+    len: usize,
+    words: Vec<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Automaton {
+    width: usize,
+    /// Whether a cell is alive in the next generation, indexed by its current neighbourhood.
+    table: Vec<bool>,
+}
+
+impl RowBuilder {
+    /// Add the cell at `index`, which must come after every cell pushed so far.
+    fn push(&mut self, index: i64, alive: bool) {
+        if !alive {
+            return;
+        }
+        let offset = *self.offset.get_or_insert(index);
+        let i = (index - offset) as usize;
+        if self.words.len() <= i / 64 {
+            self.words.resize(i / 64 + 1, 0);
+        }
+        self.words[i / 64] |= 1 << (i % 64);
+        self.len = i + 1;
+    }
+
+    fn finish(self) -> Row {
+        Row {
+            offset: self.offset.unwrap_or(0),
+            len: self.len,
+            words: self.words,
+        }
+    }
+}
+
+impl Row {
+    /// A row whose cells from `offset` onwards are `cells`, and empty everywhere else.
+    pub fn new<I: IntoIterator<Item = bool>>(offset: i64, cells: I) -> Self {
+        let mut builder = RowBuilder::default();
+        for (i, alive) in cells.into_iter().enumerate() {
+            builder.push(offset + i as i64, alive);
+        }
+        builder.finish()
+    }
+
+    /// Whether the cell at `index` is alive.
+    pub fn get(&self, index: i64) -> bool {
+        self.bit(index - self.offset)
+    }
+
+    /// The indices of the live cells, in order.
+    pub fn alive(&self) -> impl Iterator<Item = i64> + '_ {
+        self.words.iter().enumerate().flat_map(move |(w, &word)| {
+            (0..64)
+                .filter(move |i| word & (1 << i) != 0)
+                .map(move |i| self.offset + (w * 64 + i) as i64)
+        })
+    }
+
+    /// The sum of the indices of the live cells.
+    pub fn sum(&self) -> i64 {
+        self.alive().sum()
+    }
+
+    /// Whether the cell `i` cells after the first live one is alive.
+    fn bit(&self, i: i64) -> bool {
+        if i < 0 || i >= self.len as i64 {
+            return false;
+        }
+        let i = i as usize;
+        self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+}
+
+impl Automaton {
+    /// An automaton where every cell dies, with neighbourhoods `width` cells wide. The width
+    /// must be odd, so that the neighbourhood is centred on the cell.
+    pub fn new(width: usize) -> Result<Self, Error> {
+        ensure!(
+            width % 2 == 1 && width <= MAX_WIDTH,
+            "Neighbourhood width should be odd and at most {}, not {}",
+            MAX_WIDTH,
+            width
+        );
+        Ok(Automaton {
+            width,
+            table: vec![false; 1 << width],
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Make cells with this neighbourhood alive in the next generation.
+    pub fn add_rule(&mut self, neighbourhood: &[bool]) -> Result<(), Error> {
+        ensure!(
+            neighbourhood.len() == self.width,
+            "Rule has {} cells, but the neighbourhood is {} cells wide",
+            neighbourhood.len(),
+            self.width
+        );
+        let index = neighbourhood
+            .iter()
+            .fold(0, |index, &alive| index << 1 | alive as usize);
+        self.table[index] = true;
+        Ok(())
+    }
+
+    /// Whether an empty neighbourhood makes a live cell, which would fill the infinite row.
+    pub fn grows_from_nothing(&self) -> bool {
+        self.table[0]
+    }
+
+    /// The next generation of `row`.
+    pub fn step(&self, row: &Row) -> Row {
+        let radius = (self.width / 2) as i64;
+        let mask = (1 << self.width) - 1;
+        let mut window = 0;
+        let mut next = RowBuilder::default();
+        // Slide the window over every cell whose neighbourhood overlaps the live cells.
+        for i in 0..row.len as i64 + 2 * radius {
+            window = (window << 1 | row.bit(i) as usize) & mask;
+            next.push(row.offset + i - radius, self.table[window]);
+        }
+        next.finish()
+    }
+
+    /// The row `generations` generations after `row`.
+    ///
+    /// Many automata settle into a pattern that repeats, possibly moving along the row each
+    /// time. Once a pattern is seen again the rest of the run is worked out from the period and
+    /// distance moved, rather than simulated.
+    pub fn run(&self, row: &Row, generations: u64) -> Row {
+        let mut history = vec![row.clone()];
+        let mut seen = HashMap::new();
+        seen.insert((row.len, row.words.clone()), 0);
+
+        for generation in 1..=generations {
+            let next = self.step(&history[history.len() - 1]);
+            let pattern = (next.len, next.words.clone());
+            if let Some(&start) = seen.get(&pattern) {
+                let period = generation - start;
+                let shift = next.offset - history[start as usize].offset;
+                let remaining = generations - generation;
+                let mut row = history[(start + remaining % period) as usize].clone();
+                if row.len > 0 {
+                    row.offset += shift * (remaining / period + 1) as i64;
+                }
+                return row;
+            }
+            seen.insert(pattern, generation);
+            history.push(next);
+        }
+        history.pop().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(offset: i64, s: &str) -> Row {
+        Row::new(offset, s.chars().map(|c| c == '#'))
+    }
+
+    /// Rule 90: each cell is the XOR of its neighbours.
+    fn rule_90() -> Automaton {
+        let mut automaton = Automaton::new(3).unwrap();
+        for &neighbourhood in &[
+            [true, true, false],
+            [true, false, false],
+            [false, true, true],
+            [false, false, true],
+        ] {
+            automaton.add_rule(&neighbourhood).unwrap();
+        }
+        automaton
+    }
+
+    #[test]
+    fn rows_are_trimmed() {
+        let r = row(-3, "..#.#..");
+        assert_eq!(r, row(-1, "#.#"));
+        assert_eq!(r.alive().collect::<Vec<_>>(), vec![-1, 1]);
+        assert!(r.get(1) && !r.get(0) && !r.get(100));
+        assert_eq!(row(5, "...."), row(0, ""));
+
+        let long = row(0, &"#".repeat(130));
+        assert_eq!(long.sum(), (0..130).sum());
+    }
+
+    #[test]
+    fn sierpinski() {
+        let automaton = rule_90();
+        let mut r = row(0, "#");
+        for expected in &["#.#", "#...#", "#.#.#.#", "#.......#"] {
+            r = automaton.step(&r);
+            assert_eq!(r, row(-(expected.len() as i64) / 2, expected));
+        }
+        assert_eq!(automaton.run(&row(0, "#"), 4), r);
+    }
+
+    #[test]
+    fn moving_pattern_is_extrapolated() {
+        // Every cell copies the one two to its left, so the pattern moves right two cells per
+        // generation.
+        let mut automaton = Automaton::new(5).unwrap();
+        for index in 0..32 {
+            if index & 0b10000 != 0 {
+                let neighbourhood: Vec<bool> =
+                    (0..5).map(|i| index & (0b10000 >> i) != 0).collect();
+                automaton.add_rule(&neighbourhood).unwrap();
+            }
+        }
+        let start = row(-1, "##.#");
+        assert_eq!(automaton.run(&start, 3), row(5, "##.#"));
+        assert_eq!(
+            automaton.run(&start, 50_000_000_000),
+            row(99_999_999_999, "##.#")
+        );
+    }
+
+    #[test]
+    fn extrapolation_matches_simulation() {
+        // A seven-cell neighbourhood where a cell is alive if exactly one of the cells around it
+        // (but not itself) is.
+        let mut automaton = Automaton::new(7).unwrap();
+        for index in 0..128usize {
+            let neighbourhood: Vec<bool> = (0..7).map(|i| index & (0b1000000 >> i) != 0).collect();
+            if neighbourhood.iter().filter(|&&alive| alive).count() == 1 && !neighbourhood[3] {
+                automaton.add_rule(&neighbourhood).unwrap();
+            }
+        }
+        let start = row(0, "##..#");
+        let mut r = start.clone();
+        for generation in 0..200 {
+            assert_eq!(
+                automaton.run(&start, generation),
+                r,
+                "generation {}",
+                generation
+            );
+            r = automaton.step(&r);
+        }
+    }
+
+    #[test]
+    fn invalid_widths() {
+        assert!(Automaton::new(4).is_err());
+        assert!(Automaton::new(MAX_WIDTH + 2).is_err());
+        assert!(rule_90().add_rule(&[true; 5]).is_err());
+    }
+}
@@ -1,13 +1,12 @@
 #![feature(external_doc)]
 #![doc(include = "../Question.md")]
-#![feature(range_contains)]
 
-use std::cmp;
-use std::collections::{HashSet, VecDeque};
-use std::mem;
+pub mod automaton;
 
 use failure::{bail, ensure, format_err, Error};
 
+use crate::automaton::{Automaton, Row};
+
 const INPUT: &str = include_str!("../input");
 
 pub fn answer() -> (String, String) {
@@ -26,10 +25,10 @@ enum Pot {
 
 #[derive(Debug)]
 struct Cave {
-    /// The initial set of filled pots.
-    initial: Vec<Pot>,
+    /// The initial set of filled pots, starting at pot 0.
+    initial: Row,
     /// Combinations of pots which result in a filled pot in the next generation.
-    rules: HashSet<[Pot; 5]>,
+    rules: Automaton,
 }
 
 impl Default for Pot {
@@ -48,28 +47,18 @@ impl Pot {
     }
 }
 
-/// Parse a rule of the form: `...## => #` into an Array of five Pots and an output Pot, where '#'
-/// is Pot::Full and '.' is Pot::Empty.
-fn parse_rule(s: &str) -> Result<([Pot; 5], Pot), Error> {
+/// Parse a rule of the form: `...## => #` into the pots it matches and an output Pot, where '#'
+/// is Pot::Full and '.' is Pot::Empty. Rules can match any number of pots.
+fn parse_rule(s: &str) -> Result<(Vec<Pot>, Pot), Error> {
     let mut words = s.split_whitespace();
     let first_word = words
         .next()
         .ok_or_else(|| format_err!("Rule requires at least one word to parse."))?;
 
-    ensure!(
-        first_word.len() == 5,
-        "First word in the rule should have a length of 5"
-    );
-
-    let mut matcher_pots = first_word.chars().map(Pot::from);
-
-    let matcher = [
-        matcher_pots.next().unwrap()?,
-        matcher_pots.next().unwrap()?,
-        matcher_pots.next().unwrap()?,
-        matcher_pots.next().unwrap()?,
-        matcher_pots.next().unwrap()?,
-    ];
+    let matcher = first_word
+        .chars()
+        .map(Pot::from)
+        .collect::<Result<Vec<_>, _>>()?;
 
     ensure!(
         words.next() == Some("=>"),
@@ -108,105 +97,30 @@ impl Cave {
 
         ensure!(lines.next() == Some(""), "Second line should be blank");
 
-        let rules: HashSet<[Pot; 5]> = lines
-            .map(|l| parse_rule(l))
-            // Only store the rules that map to a filled pot.
-            .filter_map(|rule| match rule {
-                Ok(r) if r.1 == Pot::Full => Some(Ok(r.0)),
-                Err(e) => Some(Err(e)),
-                _ => None,
-            })
-            .collect::<Result<HashSet<_>, _>>()?;
-
-        Ok(Cave { initial, rules })
+        let rules = lines.map(parse_rule).collect::<Result<Vec<_>, _>>()?;
+        // The rules say how wide the neighbourhood is (five pots in the puzzle).
+        let width = rules.first().map_or(5, |(matcher, _)| matcher.len());
+        let mut automaton = Automaton::new(width)?;
+        // Only store the rules that map to a filled pot.
+        for (matcher, _) in rules.iter().filter(|(_, output)| *output == Pot::Full) {
+            let neighbourhood: Vec<bool> = matcher.iter().map(|&p| p == Pot::Full).collect();
+            automaton.add_rule(&neighbourhood)?;
+        }
+
+        Ok(Cave {
+            initial: Row::new(0, initial.into_iter().map(|p| p == Pot::Full)),
+            rules: automaton,
+        })
     }
 
-    fn gen_sum(&self, generations: usize) -> Result<i64, Error> {
+    /// The sum of the numbers of the pots that are filled after `generations` generations.
+    fn gen_sum(&self, generations: u64) -> Result<i64, Error> {
         ensure!(
-            !self.rules.contains(&[Pot::Empty; 5]),
+            !self.rules.grows_from_nothing(),
             "Infinite plant growth possible"
         );
 
-        let initial_length = self.initial.len();
-        let mut first_index = 4;
-        let mut pots_length = initial_length + 8;
-        let mut state = VecDeque::with_capacity(pots_length + cmp::min(1000, generations));
-        state.extend((0..4).map(|_| Pot::Empty));
-        state.extend(&self.initial);
-        state.extend((0..4).map(|_| Pot::Empty));
-
-        let mut prev = state.clone();
-        let mut products = VecDeque::with_capacity(20);
-
-        for gen in 0..generations {
-            mem::swap(&mut state, &mut prev);
-            state.clear();
-            state.extend((0..pots_length).map(|n| {
-                if (2..pots_length - 2).contains(&n)
-                    && self.rules.contains(&[
-                        prev[n - 2],
-                        prev[n - 1],
-                        prev[n],
-                        prev[n + 1],
-                        prev[n + 2],
-                    ])
-                {
-                    Pot::Full
-                } else {
-                    Pot::Empty
-                }
-            }));
-            if let Some(n) = state.iter().take(4).position(|p| p == &Pot::Full) {
-                let needed = 4 - n;
-                first_index += needed;
-                pots_length += needed;
-                for _ in 0..needed {
-                    state.push_front(Pot::Empty);
-                }
-            }
-            if let Some(n) = state.iter().rev().take(4).position(|p| p == &Pot::Full) {
-                let needed = 4 - n;
-                pots_length += needed;
-                for _ in 0..needed {
-                    state.push_back(Pot::Empty);
-                }
-            }
-            debug_assert_eq!(pots_length, state.len());
-
-            let total = state
-                .iter()
-                .enumerate()
-                .filter_map(|(n, p)| match p {
-                    Pot::Full => Some(n as i64 - first_index as i64),
-                    Pot::Empty => None,
-                })
-                .sum::<i64>();
-
-            if gen > 20 {
-                products.pop_front();
-            }
-            products.push_back(total);
-            if gen > 1 {
-                let diff = products[1] - products[0];
-                if products
-                    .iter()
-                    .zip(products.iter().skip(1))
-                    .all(|(a, b)| b - a == diff)
-                {
-                    return Ok(total + (generations as i64 - gen as i64 - 1) * diff);
-                }
-            }
-        }
-
-        let out = state
-            .iter()
-            .enumerate()
-            .filter_map(|(n, p)| match p {
-                Pot::Full => Some(n as i64 - first_index as i64),
-                Pot::Empty => None,
-            })
-            .sum();
-        Ok(out)
+        Ok(self.rules.run(&self.initial, generations).sum())
     }
 }
 
@@ -236,6 +150,32 @@ mod tests {
         assert_eq!(Cave::from(input).unwrap().gen_sum(20).unwrap(), 325);
     }
 
+    #[test]
+    fn wider_rules() {
+        // Seven-pot rules where every plant spreads to the pots up to three away.
+        let mut input = String::from("initial state: #\n\n");
+        for index in 1..128 {
+            let rule: String = (0..7)
+                .map(|i| if index & (1 << i) != 0 { '#' } else { '.' })
+                .collect();
+            input.push_str(&format!("{} => #\n", rule));
+        }
+        let cave = Cave::from(&input).unwrap();
+        assert_eq!(cave.gen_sum(2).unwrap(), 0);
+        assert_eq!(
+            Cave::from("initial state: #\n\n.#..... => #\n")
+                .unwrap()
+                .gen_sum(1_000_000_000)
+                .unwrap(),
+            2_000_000_000
+        );
+        assert!(Cave::from("initial state: #\n\n...## => #\n..# => #\n").is_err());
+        assert!(Cave::from("initial state: #\n\n....... => #\n")
+            .unwrap()
+            .gen_sum(1)
+            .is_err());
+    }
+
     #[test]
     fn test_answer() {
         assert_eq!(
@@ -17,21 +17,21 @@ pub fn answer() -> (String, String) {
 }
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
-struct Point {
-    x: usize,
-    y: usize,
+pub struct Point {
+    pub x: usize,
+    pub y: usize,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum Direction {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
     Up,
     Right,
     Down,
     Left,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum Turn {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Turn {
     Left,
     Right,
     Straight,
@@ -48,17 +48,49 @@ enum Track {
     Intersection,
 }
 
+/// Carts are numbered in the order they appear in the input (top to bottom, left to right), and
+/// keep their number as they move.
 #[derive(Debug, Clone, Copy)]
 struct Cart {
+    id: usize,
     direction: Direction,
     next_turn: Turn,
 }
 
+/// Something that happened during a tick. Events are emitted in the order the carts moved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// A cart arrived at an intersection and took `turn`, leaving it facing `direction`.
+    Turned {
+        tick: u32,
+        cart: usize,
+        point: Point,
+        turn: Turn,
+        direction: Direction,
+    },
+    /// Cart `moved` drove into cart `hit`.
+    Collision {
+        tick: u32,
+        point: Point,
+        moved: usize,
+        hit: usize,
+    },
+    /// A cart was taken off the tracks after a collision.
+    Removed {
+        tick: u32,
+        cart: usize,
+        point: Point,
+    },
+}
+
+/// The tracks and the carts on them. Displaying a map draws the current frame, with an `X` where
+/// any collisions happened during the last tick.
 #[derive(Debug, Clone)]
-struct Map {
+pub struct Map {
     tick: u32,
     tracks: Vec<Vec<Option<Track>>>,
     carts: HashMap<Point, Cart>,
+    collisions: Vec<Point>,
 }
 
 impl Track {
@@ -74,28 +106,44 @@ impl Track {
     }
 }
 
+impl Direction {
+    fn turn(self, turn: Turn) -> Self {
+        match (turn, self) {
+            (Turn::Straight, _) => self,
+            (Turn::Left, Direction::Up) | (Turn::Right, Direction::Down) => Direction::Left,
+            (Turn::Left, Direction::Down) | (Turn::Right, Direction::Up) => Direction::Right,
+            (Turn::Left, Direction::Left) | (Turn::Right, Direction::Right) => Direction::Down,
+            (Turn::Left, Direction::Right) | (Turn::Right, Direction::Left) => Direction::Up,
+        }
+    }
+}
+
+impl Turn {
+    /// The turn a cart takes at the intersection after this one.
+    fn next(self) -> Self {
+        match self {
+            Turn::Left => Turn::Straight,
+            Turn::Straight => Turn::Right,
+            Turn::Right => Turn::Left,
+        }
+    }
+}
+
 impl Cart {
     /// Returns Some(Cart) if there's a cart, else None.
-    fn from(c: char) -> Option<Self> {
-        match c {
-            '^' => Some(Cart {
-                direction: Direction::Up,
-                next_turn: Turn::Left,
-            }),
-            'v' => Some(Cart {
-                direction: Direction::Down,
-                next_turn: Turn::Left,
-            }),
-            '<' => Some(Cart {
-                direction: Direction::Left,
-                next_turn: Turn::Left,
-            }),
-            '>' => Some(Cart {
-                direction: Direction::Right,
-                next_turn: Turn::Left,
-            }),
-            _ => None,
-        }
+    fn from(c: char, id: usize) -> Option<Self> {
+        let direction = match c {
+            '^' => Direction::Up,
+            'v' => Direction::Down,
+            '<' => Direction::Left,
+            '>' => Direction::Right,
+            _ => return None,
+        };
+        Some(Cart {
+            id,
+            direction,
+            next_turn: Turn::Left,
+        })
     }
 }
 
@@ -113,7 +161,9 @@ impl fmt::Display for Map {
                 write!(
                     f,
                     "{}",
-                    if self.carts.contains_key(&Point { x, y }) {
+                    if self.collisions.contains(&Point { x, y }) {
+                        'X'
+                    } else if self.carts.contains_key(&Point { x, y }) {
                         match self.carts[&Point { x, y }].direction {
                             Direction::Up => '^',
                             Direction::Right => '>',
@@ -139,7 +189,7 @@ impl fmt::Display for Map {
 }
 
 impl Map {
-    fn from(s: &str) -> Result<Self, Error> {
+    pub fn from(s: &str) -> Result<Self, Error> {
         let y_count = s.lines().count();
         let x_count = s
             .lines()
@@ -152,7 +202,7 @@ impl Map {
         for (y, line) in s.lines().enumerate() {
             for (x, c) in line.chars().enumerate() {
                 tracks[y].push(Track::from(c));
-                if let Some(cart) = Cart::from(c) {
+                if let Some(cart) = Cart::from(c, carts.len()) {
                     carts.insert(Point { x, y }, cart);
                 }
             }
@@ -161,130 +211,162 @@ impl Map {
             tracks,
             carts,
             tick: 0,
+            collisions: Vec::new(),
         })
     }
 
-    fn crash(&mut self) -> Point {
-        loop {
-            let mut carts_vec: Vec<(Point, Cart)> =
-                self.carts.iter().map(|(&p, &c)| (p, c)).collect();
-            carts_vec.sort_by_key(|(p, _)| (p.y, p.x));
-            let mut output = None;
-            for (point, cart) in &carts_vec {
-                if output.is_some() && &output.unwrap() == point {
-                    continue;
+    /// The number of ticks simulated so far.
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// The position, number and direction of each cart still on the tracks, in the order they
+    /// will next move.
+    pub fn carts(&self) -> Vec<(Point, usize, Direction)> {
+        let mut carts: Vec<_> = self
+            .carts
+            .iter()
+            .map(|(&p, cart)| (p, cart.id, cart.direction))
+            .collect();
+        carts.sort_by_key(|&(p, _, _)| (p.y, p.x));
+        carts
+    }
+
+    /// Move every cart one step, returning what happened along the way.
+    ///
+    /// Panics if a cart runs off the end of the tracks.
+    pub fn step(&mut self) -> Vec<Event> {
+        self.tick += 1;
+        self.collisions.clear();
+        let tick = self.tick;
+        let mut events = Vec::new();
+
+        for (point, _, _) in self.carts() {
+            // The cart may have been hit by another cart earlier in this tick.
+            let mut cart = match self.carts.remove(&point) {
+                Some(cart) => cart,
+                None => continue,
+            };
+            let Point { mut x, mut y } = point;
+            match cart.direction {
+                Direction::Up => y -= 1,
+                Direction::Down => y += 1,
+                Direction::Left => x -= 1,
+                Direction::Right => x += 1,
+            }
+            let new_point = Point { x, y };
+
+            match self.tracks[y][x] {
+                Some(Track::Horizontal) => {
+                    debug_assert!(
+                        cart.direction == Direction::Left || cart.direction == Direction::Right
+                    );
                 }
-                self.carts.remove(point);
-                let Point { mut x, mut y } = point;
-                match cart.direction {
-                    Direction::Up => y -= 1,
-                    Direction::Down => y += 1,
-                    Direction::Left => x -= 1,
-                    Direction::Right => x += 1,
+                Some(Track::Vertical) => {
+                    debug_assert!(
+                        cart.direction == Direction::Up || cart.direction == Direction::Down
+                    );
                 }
-                let new_point = Point { x, y };
-
-                let mut new_direction = cart.direction;
-                let mut new_next_turn = cart.next_turn;
-                match self.tracks[y][x] {
-                    Some(Track::Horizontal) => {
-                        debug_assert!(
-                            cart.direction == Direction::Left || cart.direction == Direction::Right
-                        );
+                Some(Track::ForwardCorner) => {
+                    cart.direction = match cart.direction {
+                        Direction::Up => Direction::Right,
+                        Direction::Down => Direction::Left,
+                        Direction::Left => Direction::Down,
+                        Direction::Right => Direction::Up,
                     }
-                    Some(Track::Vertical) => {
-                        debug_assert!(
-                            cart.direction == Direction::Up || cart.direction == Direction::Down
-                        );
-                    }
-                    Some(Track::ForwardCorner) => {
-                        new_direction = match cart.direction {
-                            Direction::Up => Direction::Right,
-                            Direction::Down => Direction::Left,
-                            Direction::Left => Direction::Down,
-                            Direction::Right => Direction::Up,
-                        }
-                    }
-                    Some(Track::BackCorner) => {
-                        new_direction = match cart.direction {
-                            Direction::Up => Direction::Left,
-                            Direction::Down => Direction::Right,
-                            Direction::Left => Direction::Up,
-                            Direction::Right => Direction::Down,
-                        }
-                    }
-                    Some(Track::Intersection) => match cart.next_turn {
-                        Turn::Left => {
-                            new_next_turn = Turn::Straight;
-                            new_direction = match cart.direction {
-                                Direction::Up => Direction::Left,
-                                Direction::Down => Direction::Right,
-                                Direction::Left => Direction::Down,
-                                Direction::Right => Direction::Up,
-                            }
-                        }
-                        Turn::Straight => {
-                            new_next_turn = Turn::Right;
-                        }
-                        Turn::Right => {
-                            new_next_turn = Turn::Left;
-                            new_direction = match cart.direction {
-                                Direction::Up => Direction::Right,
-                                Direction::Down => Direction::Left,
-                                Direction::Left => Direction::Up,
-                                Direction::Right => Direction::Down,
-                            }
-                        }
-                    },
-                    None => {
-                        panic!("How did we get here?");
+                }
+                Some(Track::BackCorner) => {
+                    cart.direction = match cart.direction {
+                        Direction::Up => Direction::Left,
+                        Direction::Down => Direction::Right,
+                        Direction::Left => Direction::Up,
+                        Direction::Right => Direction::Down,
                     }
                 }
-
-                if let Entry::Occupied(entry) = self.carts.entry(new_point) {
-                    output = Some(new_point);
-                    entry.remove();
-                } else {
-                    self.carts.insert(
-                        new_point,
-                        Cart {
-                            direction: new_direction,
-                            next_turn: new_next_turn,
-                        },
-                    );
+                Some(Track::Intersection) => {
+                    let turn = cart.next_turn;
+                    cart.direction = cart.direction.turn(turn);
+                    cart.next_turn = turn.next();
+                    events.push(Event::Turned {
+                        tick,
+                        cart: cart.id,
+                        point: new_point,
+                        turn,
+                        direction: cart.direction,
+                    });
+                }
+                None => {
+                    panic!("Cart {} ran off the tracks at {}", cart.id, new_point);
                 }
             }
-            if output.is_some() {
-                return output.unwrap();
+
+            if let Entry::Occupied(entry) = self.carts.entry(new_point) {
+                let hit = entry.remove();
+                self.collisions.push(new_point);
+                events.push(Event::Collision {
+                    tick,
+                    point: new_point,
+                    moved: cart.id,
+                    hit: hit.id,
+                });
+                for id in &[cart.id, hit.id] {
+                    events.push(Event::Removed {
+                        tick,
+                        cart: *id,
+                        point: new_point,
+                    });
+                }
+            } else {
+                self.carts.insert(new_point, cart);
             }
         }
+        events
     }
 
-    fn last_cart_standing(&mut self) -> Point {
+    /// Run until the first collision, returning where it happened. The rest of that tick is
+    /// still simulated.
+    pub fn crash(&mut self) -> Point {
         loop {
-            if self.carts.len() == 1 {
-                return self.carts.keys().next().unwrap().to_owned();
+            let collision = self.step().into_iter().find_map(|event| match event {
+                Event::Collision { point, .. } => Some(point),
+                _ => None,
+            });
+            if let Some(point) = collision {
+                return point;
             }
-            self.crash();
         }
     }
+
+    /// Run until there is only one cart left, returning its position at the end of that tick.
+    ///
+    /// Panics if the carts collide until there are none left.
+    pub fn last_cart_standing(&mut self) -> Point {
+        while self.carts.len() > 1 {
+            self.step();
+        }
+        self.carts
+            .keys()
+            .next()
+            .cloned()
+            .expect("Expected an odd number of carts")
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn first_example() {
-        let input = r#"/->-\        
+    const FIRST_EXAMPLE: &str = r#"/->-\        
 |   |  /----\
 | /-+--+-\  |
 | | |  | v  |
 \-+-/  \-+--/
   \------/   "#;
 
+    #[test]
+    fn first_example() {
         assert_eq!(
-            Map::from(input).unwrap().clone().crash(),
+            Map::from(FIRST_EXAMPLE).unwrap().clone().crash(),
             Point { x: 7, y: 3 }
         );
     }
@@ -294,6 +376,102 @@ mod tests {
         assert_eq!(answer(), (String::from("83,49"), String::from("73,36")));
     }
 
+    #[test]
+    fn frames() {
+        let mut map = Map::from(FIRST_EXAMPLE).unwrap();
+        map.step();
+        assert_eq!(
+            map.to_string(),
+            r#"/-->\        
+|   |  /----\
+| /-+--+-\  |
+| | |  | |  |
+\-+-/  \->--/
+  \------/   
+"#
+        );
+        assert_eq!(
+            map.carts(),
+            vec![
+                (Point { x: 3, y: 0 }, 0, Direction::Right),
+                (Point { x: 9, y: 4 }, 1, Direction::Right)
+            ]
+        );
+
+        while map.tick() < 13 {
+            map.step();
+        }
+        assert_eq!(
+            map.to_string(),
+            r#"/---\        
+|   |  /----\
+| /-+--v-\  |
+| | |  | |  |
+\-+-/  ^-+--/
+  \------/   
+"#
+        );
+        assert_eq!(
+            map.step(),
+            vec![
+                Event::Collision {
+                    tick: 14,
+                    point: Point { x: 7, y: 3 },
+                    moved: 0,
+                    hit: 1,
+                },
+                Event::Removed {
+                    tick: 14,
+                    cart: 0,
+                    point: Point { x: 7, y: 3 },
+                },
+                Event::Removed {
+                    tick: 14,
+                    cart: 1,
+                    point: Point { x: 7, y: 3 },
+                },
+            ]
+        );
+        assert_eq!(
+            map.to_string(),
+            r#"/---\        
+|   |  /----\
+| /-+--+-\  |
+| | |  X |  |
+\-+-/  \-+--/
+  \------/   
+"#
+        );
+    }
+
+    #[test]
+    fn turns() {
+        let mut map = Map::from(FIRST_EXAMPLE).unwrap();
+        let turns: Vec<Event> = (0..14)
+            .flat_map(|_| map.step())
+            .filter(|event| matches!(event, Event::Turned { .. }))
+            .collect();
+        assert_eq!(
+            turns[0],
+            Event::Turned {
+                tick: 1,
+                cart: 1,
+                point: Point { x: 9, y: 4 },
+                turn: Turn::Left,
+                direction: Direction::Right,
+            }
+        );
+        let cart_0_turns: Vec<Turn> = turns
+            .iter()
+            .filter_map(|event| match *event {
+                Event::Turned { cart: 0, turn, .. } => Some(turn),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(turns.len(), 5);
+        assert_eq!(cart_0_turns, vec![Turn::Left, Turn::Straight, Turn::Right]);
+    }
+
     #[test]
     fn second_example() {
         let input = r#"/>-<\  
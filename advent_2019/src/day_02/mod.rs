@@ -1,15 +1,12 @@
-use snafu::{OptionExt, ResultExt, Snafu};
+use advent_2019::intcode::{self, Machine, Value};
+use snafu::{ResultExt, Snafu};
 
 const INPUT: &str = include_str!("input");
 
 #[derive(Debug, Snafu, PartialEq)]
 pub enum Error {
-    #[snafu(display("Invalid opcode: {}.", opcode))]
-    InvalidOpcode { opcode: i32 },
-    #[snafu(display("Out of range: {}.", position))]
-    OutOfRange { position: usize },
-    #[snafu(display("Invalid input: {}.", source))]
-    InvalidInput { source: std::num::ParseIntError },
+    #[snafu(display("Intcode error: {}.", source))]
+    Intcode { source: intcode::Error },
     #[snafu(display("Expected output was not found."))]
     OutputNotFound,
 }
@@ -21,90 +18,37 @@ pub fn answer() -> (String, String) {
     )
 }
 
-trait GetChecked {
-    fn get_error(&self, i: usize) -> Result<i32, Error>;
-    fn get_mut_error(&mut self, i: usize) -> Result<&mut i32, Error>;
+/// Run the program with the noun and verb written to addresses 1 and 2, returning address 0.
+fn run_with(int_codes: &[Value], noun: Value, verb: Value) -> Result<Value, Error> {
+    let mut machine = Machine::new(int_codes.to_vec());
+    machine.write(1, noun).context(Intcode)?;
+    machine.write(2, verb).context(Intcode)?;
+    machine.run(&[]).context(Intcode)?;
+    Ok(machine.read(0))
 }
 
-impl GetChecked for Vec<i32> {
-    fn get_error(&self, i: usize) -> Result<i32, Error> {
-        self.get(i).copied().context(OutOfRange { position: i })
-    }
-
-    fn get_mut_error(&mut self, i: usize) -> Result<&mut i32, Error> {
-        self.get_mut(i).context(OutOfRange { position: i })
-    }
-}
-
-fn first(input: &str) -> Result<i32, Error> {
-    let mut int_codes = input
-        .trim()
-        .split_terminator(',')
-        .map(|i| i.parse::<i32>().context(InvalidInput))
-        .collect::<Result<Vec<_>, Error>>()?;
-
-    *int_codes.get_mut_error(1)? = 12;
-    *int_codes.get_mut_error(2)? = 2;
-
-    run_program(&mut int_codes)?;
-    int_codes.get_error(0)
+fn first(input: &str) -> Result<Value, Error> {
+    let int_codes = intcode::parse(input).context(Intcode)?;
+    run_with(&int_codes, 12, 2)
 }
 
-fn second(input: &str, output: i32) -> Result<i32, Error> {
-    let int_codes = input
-        .trim()
-        .split_terminator(',')
-        .map(|i| i.parse::<i32>().context(InvalidInput))
-        .collect::<Result<Vec<_>, Error>>()?;
+fn second(input: &str, output: Value) -> Result<Value, Error> {
+    let int_codes = intcode::parse(input).context(Intcode)?;
 
     for i in 0..100 {
         for j in 0..100 {
-            let mut int_codes = int_codes.clone();
-            *int_codes.get_mut_error(1)? = i;
-            *int_codes.get_mut_error(2)? = j;
-            run_program(&mut int_codes)?;
-            if output == int_codes.get_error(0)? {
-                return Ok(int_codes.get_error(1)? * 100 + int_codes.get_error(2)?);
+            if output == run_with(&int_codes, i, j)? {
+                return Ok(i * 100 + j);
             }
         }
     }
     OutputNotFound.fail()
 }
 
-fn run_program(int_codes: &mut Vec<i32>) -> Result<(), Error> {
-    let mut program_counter = 0;
-
-    while let Some(opcode) = int_codes.get(program_counter) {
-        match opcode {
-            // Add: `1 2 3 4` -> add 2 and 3 and write to position 4.
-            1 => {
-                let a = int_codes.get_error(int_codes.get_error(program_counter + 1)? as usize)?;
-                let b = int_codes.get_error(int_codes.get_error(program_counter + 2)? as usize)?;
-                let position = int_codes.get_error(program_counter + 3)? as usize;
-                int_codes[position] = a + b;
-                program_counter += 4;
-            }
-            // Multiply: `2 3 4 5` -> multiply 3 and 4 and write to position 5.
-            2 => {
-                let a = int_codes.get_error(int_codes.get_error(program_counter + 1)? as usize)?;
-                let b = int_codes.get_error(int_codes.get_error(program_counter + 2)? as usize)?;
-                let position = int_codes.get_error(program_counter + 3)? as usize;
-                int_codes[position] = a * b;
-                program_counter += 4;
-            }
-            99 => {
-                break;
-            }
-            _ => InvalidOpcode { opcode: *opcode }.fail()?,
-        }
-    }
-
-    Ok(())
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use advent_2019::intcode::run_program;
 
     #[test]
     fn first_examples() {
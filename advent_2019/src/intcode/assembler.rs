@@ -0,0 +1,340 @@
+//! A small assembler producing Intcode images, so test programs can be written by name.
+//!
+//! One statement per line, with `;` starting a comment:
+//!
+//! ```text
+//! loop:   in [x]              ; labels end with a colon
+//!         jf [x] end          ; a bare label is its address, as an immediate value
+//!         mul [x] 2 [rb+1]    ; [n] is position mode, [rb+n] is relative mode
+//!         out [rb+1]
+//!         jt 1 loop
+//! end:    hlt
+//! x:      data 0              ; data emits raw values
+//! ```
+//!
+//! Operands are integers, labels or `label+n`/`label-n`, optionally wrapped in `[...]` for
+//! position mode. The mnemonics are the ones printed by the [disassembler](super::disassembler).
+
+use super::{
+    DuplicateLabel, Error, ImmediateDestination, Instruction, InvalidOperand, Mode, Opcode,
+    OperandCount, UndefinedLabel, UnknownMnemonic, Value,
+};
+use snafu::{ensure, OptionExt};
+use std::collections::HashMap;
+
+/// A value that may refer to a label, to be resolved once every label's address is known.
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(Value),
+    Label { name: String, offset: Value },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Operand {
+    mode: Mode,
+    expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Body {
+    Instruction(Opcode, Vec<Operand>),
+    Data(Vec<Expr>),
+}
+
+impl Body {
+    fn len(&self) -> usize {
+        match self {
+            Body::Instruction(opcode, _) => opcode.parameter_count() + 1,
+            Body::Data(values) => values.len(),
+        }
+    }
+}
+
+/// A parsed source line, with its 1-based line number for error messages.
+#[derive(Debug, Clone, PartialEq)]
+struct Statement {
+    line: usize,
+    labels: Vec<String>,
+    body: Option<Body>,
+}
+
+/// Assemble `source` into an image that can be loaded into a [`Machine`](super::Machine).
+pub fn assemble(source: &str) -> Result<Vec<Value>, Error> {
+    let statements = source
+        .lines()
+        .enumerate()
+        .map(|(i, text)| parse_line(i + 1, text))
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let mut labels = HashMap::new();
+    let mut address = 0;
+    for statement in &statements {
+        for label in &statement.labels {
+            ensure!(
+                labels.insert(label.clone(), address).is_none(),
+                DuplicateLabel {
+                    line: statement.line,
+                    label: label.clone(),
+                }
+            );
+        }
+        address += statement.body.as_ref().map_or(0, Body::len);
+    }
+
+    let mut image = Vec::with_capacity(address);
+    for statement in &statements {
+        let value_of = |expr: &Expr| resolve(expr, &labels, statement.line);
+        match &statement.body {
+            None => {}
+            Some(Body::Data(values)) => {
+                for value in values {
+                    image.push(value_of(value)?);
+                }
+            }
+            Some(Body::Instruction(opcode, operands)) => {
+                let mut modes = [Mode::Position; 3];
+                for (mode, operand) in modes.iter_mut().zip(operands) {
+                    *mode = operand.mode;
+                }
+                let opcode = *opcode;
+                image.push(Instruction { opcode, modes }.encode());
+                for operand in operands {
+                    image.push(value_of(&operand.expr)?);
+                }
+            }
+        }
+    }
+    Ok(image)
+}
+
+fn resolve(expr: &Expr, labels: &HashMap<String, usize>, line: usize) -> Result<Value, Error> {
+    match expr {
+        Expr::Number(value) => Ok(*value),
+        Expr::Label { name, offset } => {
+            let address = labels.get(name).context(UndefinedLabel {
+                line,
+                label: name.clone(),
+            })?;
+            Ok(*address as Value + offset)
+        }
+    }
+}
+
+fn parse_line(line: usize, text: &str) -> Result<Statement, Error> {
+    let text = text.split(';').next().unwrap_or("");
+    let mut words = text.split_whitespace().peekable();
+
+    let mut labels = Vec::new();
+    while let Some(label) = words.peek().and_then(|word| word.strip_suffix(':')) {
+        ensure!(
+            is_identifier(label),
+            InvalidOperand {
+                line,
+                operand: label
+            }
+        );
+        labels.push(label.to_string());
+        words.next();
+    }
+
+    let body = match words.next() {
+        None => None,
+        Some("data") => Some(Body::Data(
+            words
+                .map(|word| parse_expr(line, word))
+                .collect::<Result<_, _>>()?,
+        )),
+        Some(mnemonic) => {
+            let opcode =
+                Opcode::from_mnemonic(mnemonic).context(UnknownMnemonic { line, mnemonic })?;
+            let operands = words
+                .map(|word| parse_operand(line, word))
+                .collect::<Result<Vec<_>, _>>()?;
+            ensure!(
+                operands.len() == opcode.parameter_count(),
+                OperandCount {
+                    line,
+                    expected: opcode.parameter_count(),
+                    found: operands.len(),
+                }
+            );
+            if let Some(n) = opcode.write_parameter() {
+                ensure!(
+                    operands[n].mode != Mode::Immediate,
+                    ImmediateDestination { line }
+                );
+            }
+            Some(Body::Instruction(opcode, operands))
+        }
+    };
+
+    Ok(Statement { line, labels, body })
+}
+
+fn parse_operand(line: usize, word: &str) -> Result<Operand, Error> {
+    let inner = match word.strip_prefix('[').and_then(|w| w.strip_suffix(']')) {
+        Some(inner) => inner,
+        None => {
+            return Ok(Operand {
+                mode: Mode::Immediate,
+                expr: parse_expr(line, word)?,
+            })
+        }
+    };
+    let (mode, expr) = match inner.strip_prefix("rb") {
+        Some("") => (Mode::Relative, Expr::Number(0)),
+        Some(offset) if offset.starts_with('+') => {
+            (Mode::Relative, parse_expr(line, &offset[1..])?)
+        }
+        Some(offset) if offset.starts_with('-') => (Mode::Relative, parse_expr(line, offset)?),
+        _ => (Mode::Position, parse_expr(line, inner)?),
+    };
+    Ok(Operand { mode, expr })
+}
+
+fn parse_expr(line: usize, word: &str) -> Result<Expr, Error> {
+    if let Ok(value) = word.parse() {
+        return Ok(Expr::Number(value));
+    }
+    let split = word.find(['+', '-']).unwrap_or(word.len());
+    let (name, offset) = word.split_at(split);
+    let offset = match offset.strip_prefix('+') {
+        _ if offset.is_empty() => Some(0),
+        Some(positive) => positive.parse().ok(),
+        None => offset.parse().ok(),
+    };
+    match offset {
+        Some(offset) if is_identifier(name) => Ok(Expr::Label {
+            name: name.to_string(),
+            offset,
+        }),
+        _ => InvalidOperand {
+            line,
+            operand: word,
+        }
+        .fail(),
+    }
+}
+
+fn is_identifier(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && word.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{disassembler::disassemble, run_program, Machine};
+
+    #[test]
+    fn day_02_example() {
+        let source = "
+            add [a] [b] [3]
+            mul [3] [c] [0]
+            hlt
+        a:  data 30
+        b:  data 40
+        c:  data 50
+        ";
+        let mut image = assemble(source).unwrap();
+        assert_eq!(image, vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+        run_program(&mut image).unwrap();
+        assert_eq!(image[0], 3500);
+    }
+
+    #[test]
+    fn labels_and_relative_mode() {
+        // Output double each input until a zero is read.
+        let source = "
+        loop:   in [x]              ; read a value
+                jf [x] end
+                arb 100
+                mul [x] 2 [rb+1]
+                out [rb+1]
+                arb -100
+                jt 1 loop
+        end:    hlt
+        x:      data 0
+        ";
+        let image = assemble(source).unwrap();
+        assert_eq!(Machine::new(image).run(&[3, 5, 21, 0]), Ok(vec![6, 10, 42]));
+    }
+
+    #[test]
+    fn label_offsets() {
+        let image = assemble("out [table+2]\nhlt\ntable: data 7 8 9 table-1").unwrap();
+        assert_eq!(image, vec![4, 5, 99, 7, 8, 9, 2]);
+        assert_eq!(Machine::new(image).run(&[]), Ok(vec![9]));
+    }
+
+    #[test]
+    fn round_trip_through_disassembler() {
+        let program = vec![
+            3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0,
+            0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4,
+            20, 1105, 1, 46, 98, 99, 109, -3, 22_201, 1, -2, 3,
+        ];
+        let source = disassemble(&program)
+            .iter()
+            .map(|line| format!("{}\n", line.item))
+            .collect::<String>();
+        assert_eq!(assemble(&source), Ok(program));
+    }
+
+    #[test]
+    fn round_trip_immediate_destinations() {
+        // `add 1 2 3` with an immediate destination, and `in 5`, can't be assembled as
+        // instructions, so they're listed as data.
+        let program = vec![11_101, 1, 2, 3, 103, 5, 104, 7, 99];
+        let source = disassemble(&program)
+            .iter()
+            .map(|line| format!("{}\n", line.item))
+            .collect::<String>();
+        assert!(source.starts_with("data 11101\n"));
+        assert_eq!(assemble(&source), Ok(program));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            assemble("hlt\nfoo 1"),
+            Err(Error::UnknownMnemonic {
+                line: 2,
+                mnemonic: "foo".to_string()
+            })
+        );
+        assert_eq!(
+            assemble("add 1 2"),
+            Err(Error::OperandCount {
+                line: 1,
+                expected: 3,
+                found: 2
+            })
+        );
+        assert_eq!(
+            assemble("in 5"),
+            Err(Error::ImmediateDestination { line: 1 })
+        );
+        assert_eq!(
+            assemble("jt 1 nowhere"),
+            Err(Error::UndefinedLabel {
+                line: 1,
+                label: "nowhere".to_string()
+            })
+        );
+        assert_eq!(
+            assemble("a: hlt\na: hlt"),
+            Err(Error::DuplicateLabel {
+                line: 2,
+                label: "a".to_string()
+            })
+        );
+        assert_eq!(
+            assemble("out [1x]"),
+            Err(Error::InvalidOperand {
+                line: 1,
+                operand: "1x".to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,418 @@
+//! An instrumented way to run a [`Machine`], recording what every instruction did.
+//!
+//! The [`Debugger`] keeps a trace of [`Event`]s, stops at breakpoints on the program counter or
+//! on memory accesses, and logs every write to watched memory cells. When a run fails the trace
+//! shows how the machine got there, and [`diff`] finds where two runs of a program part ways.
+
+use super::{disassembler::Operand, Error, Instruction, Machine, Mode, State, Value};
+use std::{collections::BTreeSet, fmt};
+
+/// A memory cell changed by an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Write {
+    pub address: usize,
+    pub old: Value,
+    pub new: Value,
+}
+
+/// Everything a single executed instruction did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// How many instructions were executed before this one.
+    pub step: usize,
+    pub pc: usize,
+    /// The relative base before the instruction ran.
+    pub relative_base: Value,
+    pub instruction: Instruction,
+    /// The parameters as they appear in memory.
+    pub operands: Vec<Operand>,
+    /// The value of every parameter the instruction reads, in order.
+    pub values: Vec<Value>,
+    /// The memory addresses read by non-immediate parameters.
+    pub reads: Vec<usize>,
+    pub write: Option<Write>,
+    pub output: Option<Value>,
+}
+
+impl Event {
+    /// Whether the instruction read or wrote `address`.
+    pub fn touches(&self, address: usize) -> bool {
+        self.reads.contains(&address) || self.write.is_some_and(|w| w.address == address)
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut text = self.instruction.opcode.mnemonic().to_string();
+        for operand in &self.operands {
+            text += &format!(" {}", operand);
+        }
+        let mut line = format!(
+            "{:>6} {:04} rb={:<4} {:<28}",
+            self.step, self.pc, self.relative_base, text
+        );
+        if !self.values.is_empty() {
+            let values = self.values.iter().map(Value::to_string).collect::<Vec<_>>();
+            line += &format!(" ({})", values.join(", "));
+        }
+        if let Some(write) = self.write {
+            line += &format!(" [{}]: {} -> {}", write.address, write.old, write.new);
+        }
+        if let Some(output) = self.output {
+            line += &format!(" out {}", output);
+        }
+        f.write_str(line.trim_end())
+    }
+}
+
+/// Where the debugger should stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Breakpoint {
+    /// Before executing the instruction at this address.
+    Pc(usize),
+    /// After any instruction that reads or writes this address.
+    Memory(usize),
+}
+
+/// Why [`Debugger::run`] returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    Halted,
+    AwaitingInput,
+    Breakpoint(Breakpoint),
+}
+
+/// A write to a watched memory cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub step: usize,
+    pub pc: usize,
+    pub write: Write,
+}
+
+/// Runs a machine one instruction at a time, keeping a trace of what happened.
+#[derive(Debug, Clone)]
+pub struct Debugger {
+    machine: Machine,
+    trace: Vec<Event>,
+    output: Vec<Value>,
+    breakpoints: BTreeSet<Breakpoint>,
+    watches: BTreeSet<usize>,
+    watch_hits: Vec<WatchHit>,
+}
+
+impl Debugger {
+    pub fn new(machine: Machine) -> Self {
+        Debugger {
+            machine,
+            trace: Vec::new(),
+            output: Vec::new(),
+            breakpoints: BTreeSet::new(),
+            watches: BTreeSet::new(),
+            watch_hits: Vec::new(),
+        }
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    pub fn push_input(&mut self, value: Value) {
+        self.machine.push_input(value);
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.insert(breakpoint);
+    }
+
+    pub fn remove_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.remove(&breakpoint);
+    }
+
+    /// Record every write to `address` in [`Debugger::watch_hits`].
+    pub fn watch(&mut self, address: usize) {
+        self.watches.insert(address);
+    }
+
+    /// Every instruction executed so far.
+    pub fn trace(&self) -> &[Event] {
+        &self.trace
+    }
+
+    /// The trace, one instruction per line.
+    pub fn trace_text(&self) -> String {
+        self.trace
+            .iter()
+            .map(|event| format!("{}\n", event))
+            .collect()
+    }
+
+    pub fn output(&self) -> &[Value] {
+        &self.output
+    }
+
+    pub fn watch_hits(&self) -> &[WatchHit] {
+        &self.watch_hits
+    }
+
+    /// Run until the machine halts, needs input, or reaches a breakpoint.
+    ///
+    /// A program counter breakpoint at the current instruction is ignored, so calling this again
+    /// after stopping at one carries on past it.
+    pub fn run(&mut self) -> Result<Stop, Error> {
+        let mut first = true;
+        loop {
+            if self.machine.is_halted() {
+                return Ok(Stop::Halted);
+            }
+            let at_pc = Breakpoint::Pc(self.machine.pc());
+            if !first && self.breakpoints.contains(&at_pc) {
+                return Ok(Stop::Breakpoint(at_pc));
+            }
+            first = false;
+            if self.step()? == State::AwaitingInput {
+                return Ok(Stop::AwaitingInput);
+            }
+            let event = self.trace.last().expect("step() records an event");
+            let hit = self.breakpoints.iter().find(|breakpoint| match breakpoint {
+                Breakpoint::Memory(address) => event.touches(*address),
+                Breakpoint::Pc(_) => false,
+            });
+            if let Some(&breakpoint) = hit {
+                return Ok(Stop::Breakpoint(breakpoint));
+            }
+        }
+    }
+
+    /// Execute a single instruction, recording it in the trace.
+    pub fn step(&mut self) -> Result<State, Error> {
+        let machine = &self.machine;
+        if machine.is_halted() {
+            return Ok(State::Halted);
+        }
+        let pc = machine.pc;
+        let instruction = Instruction::decode(machine.read(pc), pc)?;
+        let write_parameter = instruction.opcode.write_parameter();
+
+        let mut operands = Vec::new();
+        let mut values = Vec::new();
+        let mut reads = Vec::new();
+        let mut write_address = None;
+        for n in 0..instruction.opcode.parameter_count() {
+            let mode = instruction.modes[n];
+            let value = machine.read(pc + n + 1);
+            operands.push(Operand { mode, value });
+            if Some(n) == write_parameter {
+                write_address = Some(machine.param_address(instruction, n)?);
+            } else if mode == Mode::Immediate {
+                values.push(value);
+            } else {
+                let address = machine.param_address(instruction, n)?;
+                reads.push(address);
+                values.push(machine.read(address));
+            }
+        }
+        let old = write_address.map(|address| machine.read(address));
+        let relative_base = machine.relative_base;
+
+        let state = self.machine.execute(instruction)?;
+        if state == State::AwaitingInput {
+            return Ok(state);
+        }
+
+        let write = write_address.zip(old).map(|(address, old)| Write {
+            address,
+            old,
+            new: self.machine.read(address),
+        });
+        let output = match state {
+            State::Output(value) => Some(value),
+            _ => None,
+        };
+        let step = self.trace.len();
+        if let Some(write) = write.filter(|w| self.watches.contains(&w.address)) {
+            self.watch_hits.push(WatchHit { step, pc, write });
+        }
+        self.output.extend(output);
+        self.trace.push(Event {
+            step,
+            pc,
+            relative_base,
+            instruction,
+            operands,
+            values,
+            reads,
+            write,
+            output,
+        });
+        Ok(state)
+    }
+}
+
+/// The first point where two traces differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Divergence<'a> {
+    pub step: usize,
+    /// The left trace's event, or `None` if it ended first.
+    pub left: Option<&'a Event>,
+    pub right: Option<&'a Event>,
+}
+
+impl fmt::Display for Divergence<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Traces diverge at step {}:", self.step)?;
+        for (sign, event) in [("-", self.left), ("+", self.right)].iter() {
+            match event {
+                Some(event) => writeln!(f, "{} {}", sign, event)?,
+                None => writeln!(f, "{} (trace ended)", sign)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Find the first event that differs between two traces, if they aren't identical.
+pub fn diff<'a>(left: &'a [Event], right: &'a [Event]) -> Option<Divergence<'a>> {
+    (0..left.len().max(right.len()))
+        .find(|&i| left.get(i) != right.get(i))
+        .map(|step| Divergence {
+            step,
+            left: left.get(step),
+            right: right.get(step),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{assembler::assemble, Opcode};
+
+    #[test]
+    fn trace_records_operands_and_writes() {
+        let mut debugger =
+            Debugger::new(Machine::new(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]));
+        assert_eq!(debugger.run(), Ok(Stop::Halted));
+
+        let trace = debugger.trace();
+        assert_eq!(trace.len(), 3);
+        assert_eq!(trace[0].values, vec![30, 40]);
+        assert_eq!(trace[0].reads, vec![9, 10]);
+        assert_eq!(
+            trace[1].write,
+            Some(Write {
+                address: 0,
+                old: 1,
+                new: 3500
+            })
+        );
+        assert_eq!(trace[2].instruction.opcode, Opcode::Halt);
+        assert_eq!(
+            debugger.trace_text(),
+            "     0 0000 rb=0    add [9] [10] [3]             (30, 40) [3]: 3 -> 70
+     1 0004 rb=0    mul [3] [11] [0]             (70, 50) [0]: 1 -> 3500
+     2 0008 rb=0    hlt
+"
+        );
+    }
+
+    #[test]
+    fn breakpoints_and_watches() {
+        let image = assemble(
+            "
+        loop:   in [x]
+                add [total] [x] [total]
+                out [total]
+                jt [x] loop
+                hlt
+        x:      data 0
+        total:  data 0
+        ",
+        )
+        .unwrap();
+        let add = 2;
+        let total = image.len() - 1;
+
+        let mut debugger = Debugger::new(Machine::new(image));
+        debugger.add_breakpoint(Breakpoint::Pc(add));
+        debugger.watch(total);
+        for input in &[5, 6, 0] {
+            debugger.push_input(*input);
+        }
+
+        assert_eq!(debugger.run(), Ok(Stop::Breakpoint(Breakpoint::Pc(add))));
+        assert_eq!(debugger.trace().len(), 1);
+        assert_eq!(debugger.run(), Ok(Stop::Breakpoint(Breakpoint::Pc(add))));
+        assert_eq!(debugger.output(), &[5]);
+
+        debugger.remove_breakpoint(Breakpoint::Pc(add));
+        debugger.add_breakpoint(Breakpoint::Memory(total));
+        assert_eq!(
+            debugger.run(),
+            Ok(Stop::Breakpoint(Breakpoint::Memory(total)))
+        );
+        assert_eq!(debugger.machine().read(total), 11);
+
+        debugger.remove_breakpoint(Breakpoint::Memory(total));
+        assert_eq!(debugger.run(), Ok(Stop::Halted));
+        assert_eq!(debugger.output(), &[5, 11, 11]);
+        assert_eq!(
+            debugger
+                .watch_hits()
+                .iter()
+                .map(|hit| hit.write.new)
+                .collect::<Vec<_>>(),
+            vec![5, 11, 11]
+        );
+    }
+
+    #[test]
+    fn awaiting_input_is_not_traced() {
+        let mut debugger = Debugger::new(Machine::new(vec![3, 0, 99]));
+        assert_eq!(debugger.run(), Ok(Stop::AwaitingInput));
+        assert!(debugger.trace().is_empty());
+        debugger.push_input(1);
+        assert_eq!(debugger.run(), Ok(Stop::Halted));
+        assert_eq!(debugger.trace().len(), 2);
+    }
+
+    #[test]
+    fn trace_leads_up_to_error() {
+        let mut debugger = Debugger::new(Machine::new(vec![1101, 1, 41, 4, 0]));
+        assert_eq!(
+            debugger.run(),
+            Err(Error::InvalidOpcode {
+                opcode: 42,
+                position: 4
+            })
+        );
+        assert_eq!(debugger.trace().len(), 1);
+        assert_eq!(debugger.trace()[0].write.unwrap().new, 42);
+    }
+
+    #[test]
+    fn diff_noun_verb() {
+        let trace = |noun, verb| {
+            let mut machine = Machine::new(vec![1, 0, 0, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+            machine.write(1, noun).unwrap();
+            machine.write(2, verb).unwrap();
+            let mut debugger = Debugger::new(machine);
+            debugger.run().unwrap();
+            debugger.trace().to_vec()
+        };
+        let (a, b) = (trace(9, 10), trace(9, 11));
+        assert_eq!(diff(&a, &a), None);
+
+        let divergence = diff(&a, &b).unwrap();
+        assert_eq!(divergence.step, 0);
+        assert_eq!(divergence.left.unwrap().values, vec![30, 40]);
+        assert_eq!(divergence.right.unwrap().values, vec![30, 50]);
+        assert!(divergence
+            .to_string()
+            .starts_with("Traces diverge at step 0:\n- "));
+
+        let short = &b[..1];
+        let divergence = diff(&b, short).unwrap();
+        assert_eq!(divergence.step, 1);
+        assert_eq!(divergence.right, None);
+    }
+}
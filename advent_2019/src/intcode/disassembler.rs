@@ -0,0 +1,196 @@
+//! Turn an Intcode image into readable assembly.
+//!
+//! Each instruction is printed as its mnemonic followed by its operands: `5` is an immediate
+//! value, `[5]` is the value at address 5, and `[rb+5]`/`[rb-5]` are relative to the relative
+//! base. Cells that don't decode to an instruction are printed as `data`. The output (without
+//! addresses) is accepted by the [assembler](super::assembler).
+
+use super::{Instruction, Mode, Value};
+use std::fmt;
+
+/// A single instruction operand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Operand {
+    pub mode: Mode,
+    pub value: Value,
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.mode {
+            Mode::Immediate => write!(f, "{}", self.value),
+            Mode::Position => write!(f, "[{}]", self.value),
+            Mode::Relative if self.value < 0 => write!(f, "[rb{}]", self.value),
+            Mode::Relative => write!(f, "[rb+{}]", self.value),
+        }
+    }
+}
+
+/// What was found at an address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Item {
+    Instruction {
+        instruction: Instruction,
+        operands: Vec<Operand>,
+    },
+    /// A cell that isn't a valid instruction.
+    Data(Value),
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Item::Instruction {
+                instruction,
+                operands,
+            } => {
+                write!(f, "{}", instruction.opcode.mnemonic())?;
+                for operand in operands {
+                    write!(f, " {}", operand)?;
+                }
+                Ok(())
+            }
+            Item::Data(value) => write!(f, "data {}", value),
+        }
+    }
+}
+
+/// An item and the address it starts at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub address: usize,
+    pub item: Item,
+}
+
+impl Line {
+    /// Number of memory cells the item occupies (always at least one).
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        match &self.item {
+            Item::Instruction { instruction, .. } => instruction.len(),
+            Item::Data(_) => 1,
+        }
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}: {}", self.address, self.item)
+    }
+}
+
+/// Decode the item starting at `address`.
+///
+/// Anything that wouldn't encode back to the same cells (an unknown opcode, modes on parameters
+/// the opcode doesn't have, or an instruction running off the end of the image) is data. So is
+/// an instruction that writes to an immediate mode parameter, which can't run and which the
+/// assembler rejects.
+pub fn decode_at(program: &[Value], address: usize) -> Item {
+    let value = program[address];
+    let instruction = match Instruction::decode(value, address) {
+        Ok(instruction) => instruction,
+        Err(_) => return Item::Data(value),
+    };
+    let unused_modes = &instruction.modes[instruction.opcode.parameter_count()..];
+    if unused_modes.iter().any(|&mode| mode != Mode::Position) {
+        return Item::Data(value);
+    }
+    if let Some(n) = instruction.opcode.write_parameter() {
+        if instruction.modes[n] == Mode::Immediate {
+            return Item::Data(value);
+        }
+    }
+    let params = match program.get(address + 1..address + instruction.len()) {
+        Some(params) => params,
+        None => return Item::Data(value),
+    };
+    let operands = params
+        .iter()
+        .zip(instruction.modes.iter())
+        .map(|(&value, &mode)| Operand { mode, value })
+        .collect();
+    Item::Instruction {
+        instruction,
+        operands,
+    }
+}
+
+/// Disassemble a whole image, sweeping linearly from address 0.
+pub fn disassemble(program: &[Value]) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut address = 0;
+    while address < program.len() {
+        let line = Line {
+            address,
+            item: decode_at(program, address),
+        };
+        address += line.len();
+        lines.push(line);
+    }
+    lines
+}
+
+/// The disassembly as text, one line per item, each prefixed by its address.
+pub fn listing(program: &[Value]) -> String {
+    disassemble(program)
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn day_02_example() {
+        let program = [1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+        assert_eq!(
+            listing(&program),
+            "\
+0000: add [9] [10] [3]
+0004: mul [3] [11] [0]
+0008: hlt
+0009: data 30
+0010: data 40
+0011: data 50
+"
+        );
+    }
+
+    #[test]
+    fn modes() {
+        let program = [1101, 100, -1, 4, 21_201, -3, 7, 5, 109, 19, 204, -34, 99];
+        assert_eq!(
+            listing(&program),
+            "\
+0000: add 100 -1 [4]
+0004: add [rb-3] 7 [rb+5]
+0008: arb 19
+0010: out [rb-34]
+0012: hlt
+"
+        );
+    }
+
+    #[test]
+    fn undecodable_cells_are_data() {
+        // 1104 has a mode for a parameter `out` doesn't have, 103 writes to an immediate, and the
+        // `add` is truncated.
+        let program = [1104, 0, 103, 42, 1, 2];
+        assert_eq!(
+            disassemble(&program)
+                .iter()
+                .map(|line| line.item.to_string())
+                .collect::<Vec<_>>(),
+            [
+                "data 1104",
+                "data 0",
+                "data 103",
+                "data 42",
+                "data 1",
+                "data 2"
+            ]
+        );
+    }
+}
@@ -0,0 +1,671 @@
+//! An Intcode computer, shared by every day whose puzzle input is an Intcode program.
+//!
+//! Supports the full instruction set: arithmetic, input/output, jumps, comparisons and
+//! relative-base adjustment, with position, immediate and relative parameter modes. Values are
+//! `i64`, and memory grows on demand when the program writes past the end of its image, up to
+//! [`MAX_MEMORY`] cells.
+
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+use std::{collections::VecDeque, convert::TryFrom};
+
+pub mod assembler;
+pub mod debugger;
+pub mod disassembler;
+pub mod network;
+
+/// A single Intcode memory cell.
+pub type Value = i64;
+
+/// The most memory cells a machine can use. Writes beyond this fail, rather than a single bad
+/// address using up all the memory there is.
+pub const MAX_MEMORY: usize = 1 << 24;
+
+#[derive(Debug, Snafu, PartialEq)]
+pub enum Error {
+    #[snafu(display("Invalid opcode {} at position {}.", opcode, position))]
+    InvalidOpcode { opcode: Value, position: usize },
+    #[snafu(display("Invalid parameter mode {} at position {}.", mode, position))]
+    InvalidMode { mode: Value, position: usize },
+    #[snafu(display("Negative address {} at position {}.", address, position))]
+    NegativeAddress { address: Value, position: usize },
+    #[snafu(display(
+        "Address {} at position {} is beyond the memory limit.",
+        address,
+        position
+    ))]
+    OutOfRange { address: usize, position: usize },
+    #[snafu(display("Arithmetic overflow at position {}.", position))]
+    Overflow { position: usize },
+    #[snafu(display("Write to an immediate mode parameter at position {}.", position))]
+    ImmediateWrite { position: usize },
+    #[snafu(display("Input needed at position {} but none was left.", position))]
+    MissingInput { position: usize },
+    #[snafu(display("Every machine that hasn't halted is waiting for input."))]
+    Deadlock,
+    #[snafu(display("Output routed to machine {}, which doesn't exist.", machine))]
+    InvalidRoute { machine: usize },
+    #[snafu(display("Line {}: unknown mnemonic {:?}.", line, mnemonic))]
+    UnknownMnemonic { line: usize, mnemonic: String },
+    #[snafu(display("Line {}: expected {} operands, found {}.", line, expected, found))]
+    OperandCount {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    #[snafu(display("Line {}: invalid operand {:?}.", line, operand))]
+    InvalidOperand { line: usize, operand: String },
+    #[snafu(display("Line {}: the destination operand can't be immediate.", line))]
+    ImmediateDestination { line: usize },
+    #[snafu(display("Line {}: label {:?} is not defined.", line, label))]
+    UndefinedLabel { line: usize, label: String },
+    #[snafu(display("Line {}: label {:?} is already defined.", line, label))]
+    DuplicateLabel { line: usize, label: String },
+    #[snafu(display("Invalid input: {}.", source))]
+    InvalidInput { source: std::num::ParseIntError },
+}
+
+/// Parse a comma-separated Intcode program.
+pub fn parse(input: &str) -> Result<Vec<Value>, Error> {
+    input
+        .trim()
+        .split_terminator(',')
+        .map(|i| i.trim().parse::<Value>().context(InvalidInput))
+        .collect()
+}
+
+/// Run a program with no input to completion, leaving the final memory in `program`.
+pub fn run_program(program: &mut Vec<Value>) -> Result<(), Error> {
+    let mut machine = Machine::new(std::mem::take(program));
+    let result = machine.run(&[]);
+    *program = machine.memory;
+    result.map(|_| ())
+}
+
+/// How an instruction parameter should be interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// The parameter is the address of the value.
+    Position,
+    /// The parameter is the value itself.
+    Immediate,
+    /// The parameter is an address offset from the relative base.
+    Relative,
+}
+
+impl Mode {
+    fn from_digit(mode: Value, position: usize) -> Result<Self, Error> {
+        Ok(match mode {
+            0 => Mode::Position,
+            1 => Mode::Immediate,
+            2 => Mode::Relative,
+            _ => InvalidMode { mode, position }.fail()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    /// `1 a b c`: write `a + b` to `c`.
+    Add,
+    /// `2 a b c`: write `a * b` to `c`.
+    Multiply,
+    /// `3 a`: write the next input value to `a`.
+    Input,
+    /// `4 a`: output `a`.
+    Output,
+    /// `5 a b`: jump to `b` if `a` is non-zero.
+    JumpIfTrue,
+    /// `6 a b`: jump to `b` if `a` is zero.
+    JumpIfFalse,
+    /// `7 a b c`: write `1` to `c` if `a < b`, otherwise `0`.
+    LessThan,
+    /// `8 a b c`: write `1` to `c` if `a == b`, otherwise `0`.
+    Equals,
+    /// `9 a`: add `a` to the relative base.
+    AdjustRelativeBase,
+    /// `99`: stop the program.
+    Halt,
+}
+
+impl Opcode {
+    /// The opcode for the last two decimal digits of an instruction, if there is one.
+    pub fn from_value(value: Value) -> Option<Self> {
+        Some(match value {
+            1 => Opcode::Add,
+            2 => Opcode::Multiply,
+            3 => Opcode::Input,
+            4 => Opcode::Output,
+            5 => Opcode::JumpIfTrue,
+            6 => Opcode::JumpIfFalse,
+            7 => Opcode::LessThan,
+            8 => Opcode::Equals,
+            9 => Opcode::AdjustRelativeBase,
+            99 => Opcode::Halt,
+            _ => return None,
+        })
+    }
+
+    /// The numeric value of the opcode, without any parameter modes.
+    pub fn value(self) -> Value {
+        match self {
+            Opcode::Add => 1,
+            Opcode::Multiply => 2,
+            Opcode::Input => 3,
+            Opcode::Output => 4,
+            Opcode::JumpIfTrue => 5,
+            Opcode::JumpIfFalse => 6,
+            Opcode::LessThan => 7,
+            Opcode::Equals => 8,
+            Opcode::AdjustRelativeBase => 9,
+            Opcode::Halt => 99,
+        }
+    }
+
+    /// Number of parameters following the opcode.
+    pub fn parameter_count(self) -> usize {
+        match self {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => 3,
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => 2,
+            Opcode::Input | Opcode::Output | Opcode::AdjustRelativeBase => 1,
+            Opcode::Halt => 0,
+        }
+    }
+
+    /// The index of the parameter the instruction writes to, if it writes to memory.
+    pub fn write_parameter(self) -> Option<usize> {
+        match self {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => Some(2),
+            Opcode::Input => Some(0),
+            _ => None,
+        }
+    }
+
+    /// The assembly mnemonic used by the disassembler and assembler.
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Opcode::Add => "add",
+            Opcode::Multiply => "mul",
+            Opcode::Input => "in",
+            Opcode::Output => "out",
+            Opcode::JumpIfTrue => "jt",
+            Opcode::JumpIfFalse => "jf",
+            Opcode::LessThan => "lt",
+            Opcode::Equals => "eq",
+            Opcode::AdjustRelativeBase => "arb",
+            Opcode::Halt => "hlt",
+        }
+    }
+
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Self> {
+        Some(match mnemonic {
+            "add" => Opcode::Add,
+            "mul" => Opcode::Multiply,
+            "in" => Opcode::Input,
+            "out" => Opcode::Output,
+            "jt" => Opcode::JumpIfTrue,
+            "jf" => Opcode::JumpIfFalse,
+            "lt" => Opcode::LessThan,
+            "eq" => Opcode::Equals,
+            "arb" => Opcode::AdjustRelativeBase,
+            "hlt" => Opcode::Halt,
+            _ => return None,
+        })
+    }
+}
+
+/// A decoded instruction: the opcode and the mode of each of its parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub modes: [Mode; 3],
+}
+
+impl Instruction {
+    /// Decode the instruction `value` found at address `position`.
+    pub fn decode(value: Value, position: usize) -> Result<Self, Error> {
+        let opcode = Opcode::from_value(value % 100).context(InvalidOpcode {
+            opcode: value,
+            position,
+        })?;
+        ensure!(
+            (0..100_000).contains(&value),
+            InvalidOpcode {
+                opcode: value,
+                position
+            }
+        );
+        let mut modes = [Mode::Position; 3];
+        let mut digits = value / 100;
+        for mode in modes.iter_mut() {
+            *mode = Mode::from_digit(digits % 10, position)?;
+            digits /= 10;
+        }
+        Ok(Instruction { opcode, modes })
+    }
+
+    /// Encode the instruction back into its numeric form.
+    pub fn encode(self) -> Value {
+        self.modes
+            .iter()
+            .rev()
+            .fold(0, |acc, mode| acc * 10 + *mode as Value)
+            * 100
+            + self.opcode.value()
+    }
+
+    /// Length of the instruction in memory, including the opcode (so never empty).
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(self) -> usize {
+        self.opcode.parameter_count() + 1
+    }
+}
+
+/// What a machine did when asked to make progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// An instruction was executed and the machine can carry on.
+    Running,
+    /// The machine produced an output value.
+    Output(Value),
+    /// The next instruction needs input but the input queue is empty. Nothing was executed.
+    AwaitingInput,
+    /// The machine has halted.
+    Halted,
+}
+
+/// An Intcode computer with its own memory, program counter, relative base and input queue.
+///
+/// The machine can be run to completion with [`Machine::run`], or driven a step at a time with
+/// [`Machine::step`] and [`Machine::resume`], which suspend whenever it needs input that hasn't
+/// been pushed yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Machine {
+    memory: Vec<Value>,
+    pc: usize,
+    relative_base: Value,
+    halted: bool,
+    input: VecDeque<Value>,
+}
+
+impl Machine {
+    pub fn new(program: Vec<Value>) -> Self {
+        Machine {
+            memory: program,
+            pc: 0,
+            relative_base: 0,
+            halted: false,
+            input: VecDeque::new(),
+        }
+    }
+
+    /// The machine's memory, as far as it has been written.
+    pub fn memory(&self) -> &[Value] {
+        &self.memory
+    }
+
+    /// Read a memory cell. Cells past the end of memory are zero.
+    pub fn read(&self, address: usize) -> Value {
+        self.memory.get(address).copied().unwrap_or(0)
+    }
+
+    /// Write a memory cell, growing memory if needed.
+    pub fn write(&mut self, address: usize, value: Value) -> Result<(), Error> {
+        ensure!(
+            address < MAX_MEMORY,
+            OutOfRange {
+                address,
+                position: self.pc
+            }
+        );
+        if address >= self.memory.len() {
+            self.memory.resize(address + 1, 0);
+        }
+        self.memory[address] = value;
+        Ok(())
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn relative_base(&self) -> Value {
+        self.relative_base
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Queue a value for the program's next input instruction.
+    pub fn push_input(&mut self, value: Value) {
+        self.input.push_back(value);
+    }
+
+    /// Values that have been pushed but not yet read by the program.
+    pub fn pending_input(&self) -> &VecDeque<Value> {
+        &self.input
+    }
+
+    /// Whether the machine is stuck on an input instruction with nothing queued.
+    pub fn is_awaiting_input(&self) -> bool {
+        !self.halted
+            && self.input.is_empty()
+            && Opcode::from_value(self.read(self.pc) % 100) == Some(Opcode::Input)
+    }
+
+    /// Run until the program halts, feeding it `input` and returning everything it outputs.
+    ///
+    /// Running out of input is an error; use [`Machine::resume`] to suspend instead.
+    pub fn run(&mut self, input: &[Value]) -> Result<Vec<Value>, Error> {
+        self.input.extend(input);
+        let mut output = Vec::new();
+        loop {
+            match self.resume()? {
+                State::Output(value) => output.push(value),
+                State::Halted => return Ok(output),
+                State::AwaitingInput => MissingInput { position: self.pc }.fail()?,
+                State::Running => unreachable!("resume() never stops while running"),
+            }
+        }
+    }
+
+    /// Run until the machine produces an output, needs input it doesn't have, or halts.
+    pub fn resume(&mut self) -> Result<State, Error> {
+        loop {
+            match self.step()? {
+                State::Running => continue,
+                state => return Ok(state),
+            }
+        }
+    }
+
+    /// Execute the single instruction at the program counter.
+    pub fn step(&mut self) -> Result<State, Error> {
+        if self.halted {
+            return Ok(State::Halted);
+        }
+        let instruction = Instruction::decode(self.read(self.pc), self.pc)?;
+        self.execute(instruction)
+    }
+
+    /// Execute a single decoded instruction at the program counter.
+    fn execute(&mut self, instruction: Instruction) -> Result<State, Error> {
+        let mut next_pc = self.pc + instruction.len();
+        let mut state = State::Running;
+        match instruction.opcode {
+            Opcode::Add => {
+                let (a, b) = (self.param(instruction, 0)?, self.param(instruction, 1)?);
+                let value = self.checked(a.checked_add(b))?;
+                self.write_param(instruction, 2, value)?;
+            }
+            Opcode::Multiply => {
+                let (a, b) = (self.param(instruction, 0)?, self.param(instruction, 1)?);
+                let value = self.checked(a.checked_mul(b))?;
+                self.write_param(instruction, 2, value)?;
+            }
+            Opcode::Input => {
+                let value = match self.input.front() {
+                    Some(&value) => value,
+                    None => return Ok(State::AwaitingInput),
+                };
+                self.write_param(instruction, 0, value)?;
+                self.input.pop_front();
+            }
+            Opcode::Output => state = State::Output(self.param(instruction, 0)?),
+            Opcode::JumpIfTrue => {
+                if self.param(instruction, 0)? != 0 {
+                    next_pc = self.address(self.param(instruction, 1)?)?;
+                }
+            }
+            Opcode::JumpIfFalse => {
+                if self.param(instruction, 0)? == 0 {
+                    next_pc = self.address(self.param(instruction, 1)?)?;
+                }
+            }
+            Opcode::LessThan => {
+                let value = self.param(instruction, 0)? < self.param(instruction, 1)?;
+                self.write_param(instruction, 2, value as Value)?;
+            }
+            Opcode::Equals => {
+                let value = self.param(instruction, 0)? == self.param(instruction, 1)?;
+                self.write_param(instruction, 2, value as Value)?;
+            }
+            Opcode::AdjustRelativeBase => {
+                let offset = self.param(instruction, 0)?;
+                self.relative_base = self.checked(self.relative_base.checked_add(offset))?;
+            }
+            Opcode::Halt => {
+                self.halted = true;
+                next_pc = self.pc;
+                state = State::Halted;
+            }
+        }
+        self.pc = next_pc;
+        Ok(state)
+    }
+
+    /// The result of checked arithmetic, or an error if it overflowed.
+    fn checked(&self, value: Option<Value>) -> Result<Value, Error> {
+        value.context(Overflow { position: self.pc })
+    }
+
+    /// Convert a value into a memory address.
+    fn address(&self, address: Value) -> Result<usize, Error> {
+        usize::try_from(address).ok().context(NegativeAddress {
+            address,
+            position: self.pc,
+        })
+    }
+
+    /// The address the `n`th parameter refers to (not valid for immediate mode).
+    fn param_address(&self, instruction: Instruction, n: usize) -> Result<usize, Error> {
+        let raw = self.read(self.pc + n + 1);
+        match instruction.modes[n] {
+            Mode::Position => self.address(raw),
+            Mode::Relative => self.address(self.checked(self.relative_base.checked_add(raw))?),
+            Mode::Immediate => ImmediateWrite { position: self.pc }.fail(),
+        }
+    }
+
+    /// The value of the `n`th parameter.
+    fn param(&self, instruction: Instruction, n: usize) -> Result<Value, Error> {
+        match instruction.modes[n] {
+            Mode::Immediate => Ok(self.read(self.pc + n + 1)),
+            _ => Ok(self.read(self.param_address(instruction, n)?)),
+        }
+    }
+
+    /// Write `value` to the address given by the `n`th parameter.
+    fn write_param(
+        &mut self,
+        instruction: Instruction,
+        n: usize,
+        value: Value,
+    ) -> Result<(), Error> {
+        let address = self.param_address(instruction, n)?;
+        self.write(address, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_modes() {
+        assert_eq!(
+            Instruction::decode(1002, 0),
+            Ok(Instruction {
+                opcode: Opcode::Multiply,
+                modes: [Mode::Position, Mode::Immediate, Mode::Position],
+            })
+        );
+        assert_eq!(Instruction::decode(21_101, 0).unwrap().encode(), 21_101);
+        assert_eq!(
+            Instruction::decode(42, 7),
+            Err(Error::InvalidOpcode {
+                opcode: 42,
+                position: 7
+            })
+        );
+        assert_eq!(
+            Instruction::decode(301, 3),
+            Err(Error::InvalidMode {
+                mode: 3,
+                position: 3
+            })
+        );
+    }
+
+    #[test]
+    fn immediate_mode() {
+        let mut program = vec![1002, 4, 3, 4, 33];
+        run_program(&mut program).unwrap();
+        assert_eq!(program, vec![1002, 4, 3, 4, 99]);
+
+        let mut program = vec![1101, 100, -1, 4, 0];
+        run_program(&mut program).unwrap();
+        assert_eq!(program, vec![1101, 100, -1, 4, 99]);
+    }
+
+    #[test]
+    fn input_output() {
+        let program = vec![3, 0, 4, 0, 99];
+        assert_eq!(Machine::new(program.clone()).run(&[42]), Ok(vec![42]));
+        assert_eq!(
+            Machine::new(program).run(&[]),
+            Err(Error::MissingInput { position: 0 })
+        );
+    }
+
+    #[test]
+    fn comparisons_and_jumps() {
+        let run = |program: &[Value], input| Machine::new(program.to_vec()).run(&[input]).unwrap();
+
+        // Is the input equal to 8 (position mode, then immediate mode)?
+        let equal_8 = [3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+        assert_eq!(run(&equal_8, 8), vec![1]);
+        assert_eq!(run(&equal_8, 7), vec![0]);
+        let equal_8 = [3, 3, 1108, -1, 8, 3, 4, 3, 99];
+        assert_eq!(run(&equal_8, 8), vec![1]);
+        assert_eq!(run(&equal_8, 9), vec![0]);
+
+        // Is the input less than 8?
+        let less_8 = [3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8];
+        assert_eq!(run(&less_8, 5), vec![1]);
+        assert_eq!(run(&less_8, 8), vec![0]);
+        let less_8 = [3, 3, 1107, -1, 8, 3, 4, 3, 99];
+        assert_eq!(run(&less_8, 5), vec![1]);
+        assert_eq!(run(&less_8, 10), vec![0]);
+
+        // Is the input non-zero?
+        let non_zero = [3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9];
+        assert_eq!(run(&non_zero, 0), vec![0]);
+        assert_eq!(run(&non_zero, 3), vec![1]);
+        let non_zero = [3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1];
+        assert_eq!(run(&non_zero, 0), vec![0]);
+        assert_eq!(run(&non_zero, 3), vec![1]);
+
+        // 999 below 8, 1000 for 8, 1001 above 8.
+        let compare_8 = [
+            3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0,
+            0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4,
+            20, 1105, 1, 46, 98, 99,
+        ];
+        assert_eq!(run(&compare_8, 7), vec![999]);
+        assert_eq!(run(&compare_8, 8), vec![1000]);
+        assert_eq!(run(&compare_8, 9), vec![1001]);
+    }
+
+    #[test]
+    fn relative_mode_and_large_values() {
+        let quine = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        assert_eq!(Machine::new(quine.clone()).run(&[]), Ok(quine));
+
+        let output = Machine::new(vec![1102, 34_915_192, 34_915_192, 7, 4, 7, 99, 0])
+            .run(&[])
+            .unwrap();
+        assert_eq!(output[0].to_string().len(), 16);
+
+        let output = Machine::new(vec![104, 1_125_899_906_842_624, 99]).run(&[]);
+        assert_eq!(output, Ok(vec![1_125_899_906_842_624]));
+    }
+
+    #[test]
+    fn stepping_suspends_for_input() {
+        let mut machine = Machine::new(vec![3, 9, 4, 9, 3, 9, 4, 9, 99, 0]);
+        assert_eq!(machine.resume(), Ok(State::AwaitingInput));
+        assert_eq!(machine.pc(), 0);
+        machine.push_input(7);
+        assert_eq!(machine.step(), Ok(State::Running));
+        assert_eq!(machine.step(), Ok(State::Output(7)));
+        assert_eq!(machine.resume(), Ok(State::AwaitingInput));
+        machine.push_input(8);
+        assert_eq!(machine.resume(), Ok(State::Output(8)));
+        assert_eq!(machine.resume(), Ok(State::Halted));
+        assert!(machine.is_halted());
+    }
+
+    #[test]
+    fn memory_grows_on_write() {
+        let mut machine = Machine::new(vec![1101, 2, 3, 1000, 4, 1000, 99]);
+        assert_eq!(machine.run(&[]), Ok(vec![5]));
+        assert_eq!(machine.memory().len(), 1001);
+        assert_eq!(machine.read(5000), 0);
+    }
+
+    #[test]
+    fn memory_limit() {
+        let far = MAX_MEMORY as Value;
+        assert_eq!(
+            Machine::new(vec![1101, 2, 3, far, 99]).run(&[]),
+            Err(Error::OutOfRange {
+                address: MAX_MEMORY,
+                position: 0
+            })
+        );
+        let mut machine = Machine::new(vec![3, 1 << 62, 99]);
+        assert_eq!(
+            machine.run(&[1]),
+            Err(Error::OutOfRange {
+                address: 1 << 62,
+                position: 0
+            })
+        );
+        assert_eq!(machine.memory().len(), 3);
+    }
+
+    #[test]
+    fn overflow() {
+        let big = Value::MAX;
+        assert_eq!(
+            Machine::new(vec![1101, big, 1, 0, 99]).run(&[]),
+            Err(Error::Overflow { position: 0 })
+        );
+        assert_eq!(
+            Machine::new(vec![1102, big, 2, 0, 99]).run(&[]),
+            Err(Error::Overflow { position: 0 })
+        );
+        assert_eq!(
+            Machine::new(vec![109, big, 109, 1, 99]).run(&[]),
+            Err(Error::Overflow { position: 2 })
+        );
+        assert_eq!(
+            Machine::new(vec![109, big, 204, 1, 99]).run(&[]),
+            Err(Error::Overflow { position: 2 })
+        );
+    }
+
+    #[test]
+    fn negative_address() {
+        assert_eq!(
+            Machine::new(vec![4, -1, 99]).run(&[]),
+            Err(Error::NegativeAddress {
+                address: -1,
+                position: 0
+            })
+        );
+    }
+}
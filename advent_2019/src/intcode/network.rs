@@ -0,0 +1,325 @@
+//! Several Intcode machines wired together, each machine's output feeding another's input, or
+//! sent in addressed packets to whichever machine it names.
+
+use super::{Deadlock, Error, InvalidRoute, Machine, State, Value};
+use snafu::ensure;
+use std::convert::TryFrom;
+
+/// Where a machine's output values go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route {
+    /// Push outputs onto the input queue of the machine with this index.
+    Machine(usize),
+    /// Collect outputs as the network's own output.
+    External,
+    /// Read outputs in threes: an address, then two values to push onto the input queue of the
+    /// machine with that index. Packets to addresses with no machine go to the network's own
+    /// output, address and all.
+    Packets,
+}
+
+/// A set of machines connected by their input and output queues.
+///
+/// Machines are scheduled round-robin in the order they were added: in each round, each one
+/// runs until it halts or needs input it doesn't have, and every output is delivered as soon as
+/// it is produced. The same network therefore always produces the same output.
+///
+/// A machine given an idle input isn't left waiting when its queue is empty: it reads the idle
+/// value instead, once per round.
+#[derive(Debug, Clone, Default)]
+pub struct Network {
+    machines: Vec<Machine>,
+    routes: Vec<Route>,
+    idle_inputs: Vec<Option<Value>>,
+    sent: Vec<Vec<Value>>,
+    /// The start of each machine's next packet, for machines that send packets.
+    packets: Vec<Vec<Value>>,
+    output: Vec<Value>,
+}
+
+impl Network {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A chain of copies of `program`, each given its phase as its first input. Every machine
+    /// feeds the next one, and the last one's output is the network's output.
+    pub fn pipeline(program: &[Value], phases: &[Value]) -> Self {
+        let mut network = Self::new();
+        for (i, &phase) in phases.iter().enumerate() {
+            let mut machine = Machine::new(program.to_vec());
+            machine.push_input(phase);
+            let route = if i + 1 < phases.len() {
+                Route::Machine(i + 1)
+            } else {
+                Route::External
+            };
+            network.add(machine, route);
+        }
+        network
+    }
+
+    /// Like [`Network::pipeline`], but the last machine feeds back into the first.
+    pub fn feedback_loop(program: &[Value], phases: &[Value]) -> Self {
+        let mut network = Self::pipeline(program, phases);
+        if let Some(last) = network.routes.last_mut() {
+            *last = Route::Machine(0);
+        }
+        network
+    }
+
+    /// Copies of `program` that send each other packets, each given its address (its index) as
+    /// its first input, and `idle` whenever it has no packets to read.
+    pub fn packet_switched(program: &[Value], count: usize, idle: Value) -> Self {
+        let mut network = Self::new();
+        for address in 0..count {
+            let mut machine = Machine::new(program.to_vec());
+            machine.push_input(address as Value);
+            network.add(machine, Route::Packets);
+            network.set_idle_input(address, Some(idle));
+        }
+        network
+    }
+
+    /// Add a machine sending its output to `route`, returning the machine's index.
+    ///
+    /// The route can name a machine that hasn't been added yet, but it must exist by the time
+    /// the network runs.
+    pub fn add(&mut self, machine: Machine, route: Route) -> usize {
+        self.machines.push(machine);
+        self.routes.push(route);
+        self.idle_inputs.push(None);
+        self.sent.push(Vec::new());
+        self.packets.push(Vec::new());
+        self.machines.len() - 1
+    }
+
+    /// Change where machine `index`'s output goes.
+    pub fn connect(&mut self, index: usize, route: Route) -> Result<(), Error> {
+        self.check_route(route)?;
+        self.routes[index] = route;
+        Ok(())
+    }
+
+    /// Give machine `index` the value `idle` (once per round) when it needs input and has none,
+    /// or with `None`, leave it waiting.
+    pub fn set_idle_input(&mut self, index: usize, idle: Option<Value>) {
+        self.idle_inputs[index] = idle;
+    }
+
+    pub fn push_input(&mut self, index: usize, value: Value) {
+        self.machines[index].push_input(value);
+    }
+
+    pub fn machine(&self, index: usize) -> &Machine {
+        &self.machines[index]
+    }
+
+    /// Every value machine `index` has output so far, wherever it was routed.
+    pub fn sent(&self, index: usize) -> &[Value] {
+        &self.sent[index]
+    }
+
+    /// The values routed outside the network so far.
+    pub fn output(&self) -> &[Value] {
+        &self.output
+    }
+
+    /// Run every machine until all have halted, returning the values routed outside the network.
+    ///
+    /// Fails with [`Error::Deadlock`] if the machines that haven't halted are all waiting for
+    /// input that will never come. Machines with idle inputs never wait, so a network of them
+    /// only stops once they halt; use [`Network::round`] to run one a bit at a time instead.
+    pub fn run(&mut self) -> Result<&[Value], Error> {
+        while !self.machines.iter().all(Machine::is_halted) {
+            let stuck = self
+                .machines
+                .iter()
+                .zip(&self.idle_inputs)
+                .all(|(m, idle)| m.is_halted() || (m.is_awaiting_input() && idle.is_none()));
+            ensure!(!stuck, Deadlock);
+            self.round()?;
+        }
+        Ok(&self.output)
+    }
+
+    /// Give every machine one turn, returning how many values they output between them. A
+    /// round with no output and nothing left to read means the network is idle.
+    ///
+    /// Fails with [`Error::InvalidRoute`] before running anything if a route names a machine
+    /// that doesn't exist.
+    pub fn round(&mut self) -> Result<usize, Error> {
+        for &route in &self.routes {
+            self.check_route(route)?;
+        }
+        let mut sent = 0;
+        for index in 0..self.machines.len() {
+            sent += self.run_machine(index)?;
+        }
+        Ok(sent)
+    }
+
+    fn check_route(&self, route: Route) -> Result<(), Error> {
+        if let Route::Machine(target) = route {
+            ensure!(
+                target < self.machines.len(),
+                InvalidRoute { machine: target }
+            );
+        }
+        Ok(())
+    }
+
+    /// Run one machine until it halts or blocks, delivering its outputs as they appear.
+    /// Returns how many values it output.
+    fn run_machine(&mut self, index: usize) -> Result<usize, Error> {
+        let (mut sent, mut idled) = (0, false);
+        loop {
+            match self.machines[index].resume()? {
+                State::Output(value) => {
+                    sent += 1;
+                    self.deliver(index, value);
+                }
+                State::AwaitingInput => match self.idle_inputs[index] {
+                    Some(idle) if !idled => {
+                        idled = true;
+                        self.machines[index].push_input(idle);
+                    }
+                    _ => return Ok(sent),
+                },
+                _ => return Ok(sent),
+            }
+        }
+    }
+
+    /// Send a value output by machine `from` wherever it's routed.
+    fn deliver(&mut self, from: usize, value: Value) {
+        self.sent[from].push(value);
+        match self.routes[from] {
+            Route::External => self.output.push(value),
+            Route::Machine(target) => self.machines[target].push_input(value),
+            Route::Packets => {
+                self.packets[from].push(value);
+                if self.packets[from].len() < 3 {
+                    return;
+                }
+                let packet = std::mem::take(&mut self.packets[from]);
+                match usize::try_from(packet[0]) {
+                    Ok(target) if target < self.machines.len() => {
+                        self.machines[target].push_input(packet[1]);
+                        self.machines[target].push_input(packet[2]);
+                    }
+                    _ => self.output.extend(packet),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::assembler::assemble;
+
+    #[test]
+    fn amplifier_pipeline() {
+        let program = [
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ];
+        let mut network = Network::pipeline(&program, &[4, 3, 2, 1, 0]);
+        network.push_input(0, 0);
+        assert_eq!(network.run(), Ok(&[43210][..]));
+
+        let program = [
+            3, 23, 3, 24, 1002, 24, 10, 24, 1002, 23, -1, 23, 101, 5, 23, 23, 1, 24, 23, 23, 4, 23,
+            99, 0, 0,
+        ];
+        let mut network = Network::pipeline(&program, &[0, 1, 2, 3, 4]);
+        network.push_input(0, 0);
+        assert_eq!(network.run(), Ok(&[54321][..]));
+    }
+
+    #[test]
+    fn amplifier_feedback_loop() {
+        let program = [
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        let mut network = Network::feedback_loop(&program, &[9, 8, 7, 6, 5]);
+        network.push_input(0, 0);
+        assert_eq!(network.run(), Ok(&[][..]));
+        assert_eq!(network.sent(4).last(), Some(&139_629_729));
+        assert_eq!(
+            network.machine(0).pending_input().back(),
+            Some(&139_629_729)
+        );
+    }
+
+    #[test]
+    fn deadlock() {
+        // Two machines that each wait for the other before producing anything.
+        let program = vec![3, 0, 4, 0, 99];
+        let mut network = Network::new();
+        network.add(Machine::new(program.clone()), Route::Machine(1));
+        network.add(Machine::new(program), Route::Machine(0));
+        assert_eq!(network.run(), Err(Error::Deadlock));
+    }
+
+    #[test]
+    fn invalid_routes() {
+        let mut network = Network::new();
+        network.add(Machine::new(vec![104, 1, 99]), Route::Machine(3));
+        assert_eq!(network.run(), Err(Error::InvalidRoute { machine: 3 }));
+        assert_eq!(network.sent(0), &[][..]);
+        assert_eq!(
+            network.connect(0, Route::Machine(1)),
+            Err(Error::InvalidRoute { machine: 1 })
+        );
+        assert_eq!(network.connect(0, Route::External), Ok(()));
+        assert_eq!(network.run(), Ok(&[1][..]));
+    }
+
+    #[test]
+    fn packets() {
+        // Each machine passes a packet `x, y` on to the next address as `x, y + 1`, until `y`
+        // reaches 5, when it goes to address 255 instead. An input of -1 means no packet yet.
+        let source = "
+                    in [me]
+                    add [me] 1 [next]
+                    eq [next] 3 [t]
+                    jf [t] loop
+                    add 0 0 [next]
+            loop:   in [x]
+                    eq [x] -1 [t]
+                    jt [t] loop
+                    in [y]
+                    add [y] 1 [y]
+                    lt [y] 5 [t]
+                    jt [t] forward
+                    out 255
+                    jt 1 send
+            forward: out [next]
+            send:   out [x]
+                    out [y]
+                    jt 1 loop
+            me:     data 0
+            next:   data 0
+            t:      data 0
+            x:      data 0
+            y:      data 0
+        ";
+        let program = assemble(source).unwrap();
+        let mut network = Network::packet_switched(&program, 3, -1);
+        network.push_input(0, 42);
+        network.push_input(0, 0);
+        let mut rounds = 0;
+        while network.output().is_empty() && rounds < 100 {
+            network.round().unwrap();
+            rounds += 1;
+        }
+        assert_eq!(network.output(), &[255, 42, 5][..]);
+        assert_eq!(network.sent(1), &[2, 42, 2, 255, 42, 5][..]);
+        // Nobody has anything to send, but nobody is left waiting either.
+        assert_eq!(network.round(), Ok(0));
+        assert!(network.machine(2).pending_input().is_empty());
+    }
+}
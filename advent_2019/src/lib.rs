@@ -0,0 +1,4 @@
+//! Code shared between the days, built as a library so that the parts no day uses yet are still
+//! part of its public API.
+
+pub mod intcode;
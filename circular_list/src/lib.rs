@@ -0,0 +1,249 @@
+//! A circular list with a cursor, for games played by walking round a ring and inserting or
+//! removing at the current position.
+//!
+//! Nodes live in a `Vec` and link to each other by index, so inserting and removing at the cursor
+//! are O(1), and rotating the cursor by `k` is O(min(k, len - k)). Removed slots are reused by
+//! later inserts.
+
+use std::fmt;
+
+#[derive(Debug, Clone)]
+struct Node<T> {
+    /// `None` if the node has been removed and its slot is free.
+    value: Option<T>,
+    prev: usize,
+    next: usize,
+}
+
+#[derive(Clone)]
+pub struct CircularList<T> {
+    nodes: Vec<Node<T>>,
+    /// Slots in `nodes` that can be reused.
+    free: Vec<usize>,
+    /// The current node, or `None` if the list is empty.
+    cursor: Option<usize>,
+    len: usize,
+}
+
+/// Iterator over the values in a list, starting at the cursor and going forwards.
+pub struct Iter<'a, T> {
+    list: &'a CircularList<T>,
+    index: usize,
+    remaining: usize,
+}
+
+impl<T> Default for CircularList<T> {
+    fn default() -> Self {
+        CircularList {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            cursor: None,
+            len: 0,
+        }
+    }
+}
+
+impl<T> CircularList<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty list with room for `capacity` values before it reallocates.
+    pub fn with_capacity(capacity: usize) -> Self {
+        CircularList {
+            nodes: Vec::with_capacity(capacity),
+            ..Self::default()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The value at the cursor.
+    pub fn current(&self) -> Option<&T> {
+        self.cursor.and_then(|i| self.nodes[i].value.as_ref())
+    }
+
+    pub fn current_mut(&mut self) -> Option<&mut T> {
+        let nodes = &mut self.nodes;
+        self.cursor.and_then(move |i| nodes[i].value.as_mut())
+    }
+
+    /// Insert `value` after the cursor, and move the cursor onto it.
+    pub fn insert_after(&mut self, value: T) {
+        let (prev, next) = match self.cursor {
+            Some(current) => (current, self.nodes[current].next),
+            None => (self.nodes.len(), self.nodes.len()),
+        };
+        let node = Node {
+            value: Some(value),
+            prev,
+            next,
+        };
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+        if self.cursor.is_none() {
+            // A single node links to itself.
+            self.nodes[index].prev = index;
+            self.nodes[index].next = index;
+        } else {
+            self.nodes[prev].next = index;
+            self.nodes[next].prev = index;
+        }
+        self.cursor = Some(index);
+        self.len += 1;
+    }
+
+    /// Remove the value at the cursor, and move the cursor onto the value after it.
+    pub fn remove(&mut self) -> Option<T> {
+        let current = self.cursor?;
+        let Node { prev, next, .. } = self.nodes[current];
+        self.nodes[prev].next = next;
+        self.nodes[next].prev = prev;
+        self.free.push(current);
+        self.len -= 1;
+        self.cursor = if self.len == 0 { None } else { Some(next) };
+        self.nodes[current].value.take()
+    }
+
+    /// Move the cursor `k` values forwards, or backwards if `k` is negative. Goes whichever way
+    /// round the circle is shorter.
+    pub fn rotate(&mut self, k: isize) {
+        let mut current = match self.cursor {
+            Some(current) => current,
+            None => return,
+        };
+        let len = self.len as isize;
+        let forwards = ((k % len) + len) % len;
+        if forwards <= len / 2 {
+            for _ in 0..forwards {
+                current = self.nodes[current].next;
+            }
+        } else {
+            for _ in forwards..len {
+                current = self.nodes[current].prev;
+            }
+        }
+        self.cursor = Some(current);
+    }
+
+    /// Iterate over every value, starting at the cursor.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            list: self,
+            index: self.cursor.unwrap_or(0),
+            remaining: self.len,
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = &self.list.nodes[self.index];
+        self.index = node.next;
+        self.remaining -= 1;
+        node.value.as_ref()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T> IntoIterator for &'a CircularList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T> std::iter::FromIterator<T> for CircularList<T> {
+    /// A list of the values in order, with the cursor on the first one.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = CircularList::new();
+        for value in iter {
+            list.insert_after(value);
+        }
+        list.rotate(1);
+        list
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for CircularList<T> {
+    /// Lists the values starting at the cursor.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(list: &CircularList<i32>) -> Vec<i32> {
+        list.iter().cloned().collect()
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        let mut list = CircularList::new();
+        assert_eq!(list.remove(), None);
+        list.insert_after(1);
+        list.insert_after(2);
+        list.insert_after(3);
+        assert_eq!(values(&list), vec![3, 1, 2]);
+        assert_eq!(list.remove(), Some(3));
+        assert_eq!(values(&list), vec![1, 2]);
+        list.insert_after(4);
+        assert_eq!(values(&list), vec![4, 2, 1]);
+        assert_eq!(list.nodes.len(), 3, "The removed slot should be reused");
+
+        assert_eq!(list.remove(), Some(4));
+        assert_eq!(list.remove(), Some(2));
+        assert_eq!(list.remove(), Some(1));
+        assert!(list.is_empty());
+        assert_eq!(list.current(), None);
+        list.insert_after(5);
+        assert_eq!(values(&list), vec![5]);
+    }
+
+    #[test]
+    fn rotate() {
+        let mut list: CircularList<i32> = (0..5).collect();
+        assert_eq!(values(&list), vec![0, 1, 2, 3, 4]);
+        list.rotate(2);
+        assert_eq!(list.current(), Some(&2));
+        list.rotate(-3);
+        assert_eq!(list.current(), Some(&4));
+        list.rotate(4);
+        assert_eq!(list.current(), Some(&3));
+        list.rotate(-11);
+        assert_eq!(list.current(), Some(&2));
+        *list.current_mut().unwrap() = 7;
+        assert_eq!(format!("{:?}", list), "[7, 3, 4, 0, 1]");
+
+        let mut empty: CircularList<i32> = CircularList::new();
+        empty.rotate(3);
+        assert_eq!(empty.current(), None);
+    }
+}
@@ -0,0 +1,191 @@
+//! A disjoint-set forest, for grouping elements that are connected to each other.
+//!
+//! Elements are numbered `0..len`. Finding an element's set uses path compression and joining
+//! sets uses union by rank, so a sequence of operations takes effectively constant time each.
+//! Each set's members are also linked in a cycle, so they can be listed without scanning every
+//! element.
+
+#[derive(Debug, Clone, Default)]
+pub struct UnionFind {
+    /// Each element's parent, or itself if it's the root of its set.
+    parent: Vec<usize>,
+    /// An upper bound on the height of the tree below each root.
+    rank: Vec<u8>,
+    /// The number of elements in the set, for each root.
+    size: Vec<usize>,
+    /// The next element in the same set, going round in a cycle.
+    next: Vec<usize>,
+    set_count: usize,
+}
+
+impl UnionFind {
+    /// `len` elements, each in a set on its own.
+    pub fn new(len: usize) -> Self {
+        let mut union_find = UnionFind::default();
+        union_find.grow(len);
+        union_find
+    }
+
+    /// Add elements, each in a set on its own, until there are at least `len`.
+    pub fn grow(&mut self, len: usize) {
+        for element in self.len()..len {
+            self.parent.push(element);
+            self.rank.push(0);
+            self.size.push(1);
+            self.next.push(element);
+            self.set_count += 1;
+        }
+    }
+
+    /// Add one element in a set on its own, returning it.
+    pub fn add(&mut self) -> usize {
+        let element = self.len();
+        self.grow(element + 1);
+        element
+    }
+
+    /// The number of elements.
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// The number of disjoint sets.
+    pub fn set_count(&self) -> usize {
+        self.set_count
+    }
+
+    /// The representative of the set containing `element`.
+    pub fn find(&mut self, element: usize) -> usize {
+        let mut root = element;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        // Point everything on the way straight at the root.
+        let mut current = element;
+        while current != root {
+            let parent = self.parent[current];
+            self.parent[current] = root;
+            current = parent;
+        }
+        root
+    }
+
+    /// Join the sets containing `a` and `b`. Returns whether they were separate before.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        let (root, child) = if self.rank[a] < self.rank[b] {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        self.parent[child] = root;
+        if self.rank[root] == self.rank[child] {
+            self.rank[root] += 1;
+        }
+        self.size[root] += self.size[child];
+        // Swapping the successors of two elements in different cycles joins the cycles.
+        self.next.swap(root, child);
+        self.set_count -= 1;
+        true
+    }
+
+    /// Whether `a` and `b` are in the same set.
+    pub fn connected(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// The number of elements in the set containing `element`.
+    pub fn size(&mut self, element: usize) -> usize {
+        let root = self.find(element);
+        self.size[root]
+    }
+
+    /// The elements in the same set as `element` (including itself), starting with `element`.
+    pub fn members(&self, element: usize) -> Vec<usize> {
+        let mut members = vec![element];
+        let mut current = self.next[element];
+        while current != element {
+            members.push(current);
+            current = self.next[current];
+        }
+        members
+    }
+
+    /// Every set, each listed from its lowest element, in order of their lowest elements.
+    pub fn sets(&self) -> Vec<Vec<usize>> {
+        let mut seen = vec![false; self.len()];
+        let mut sets = Vec::with_capacity(self.set_count);
+        for element in 0..self.len() {
+            if !seen[element] {
+                let members = self.members(element);
+                for &member in &members {
+                    seen[member] = true;
+                }
+                sets.push(members);
+            }
+        }
+        sets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unions() {
+        let mut sets = UnionFind::new(6);
+        assert_eq!(sets.set_count(), 6);
+        assert!(sets.union(0, 1));
+        assert!(sets.union(2, 3));
+        assert!(sets.union(1, 3));
+        assert!(!sets.union(0, 2), "Already connected");
+        assert!(sets.connected(0, 3));
+        assert!(!sets.connected(0, 4));
+        assert_eq!(sets.set_count(), 3);
+        assert_eq!(sets.size(2), 4);
+        assert_eq!(sets.size(5), 1);
+
+        let mut members = sets.members(3);
+        assert_eq!(members[0], 3);
+        members.sort();
+        assert_eq!(members, vec![0, 1, 2, 3]);
+        assert_eq!(sets.members(4), vec![4]);
+        let set_sizes: Vec<usize> = sets.sets().iter().map(Vec::len).collect();
+        assert_eq!(set_sizes, vec![4, 1, 1]);
+    }
+
+    #[test]
+    fn growing() {
+        let mut sets = UnionFind::new(0);
+        assert!(sets.is_empty());
+        let a = sets.add();
+        let b = sets.add();
+        sets.union(a, b);
+        sets.grow(5);
+        assert_eq!(sets.len(), 5);
+        assert_eq!(sets.set_count(), 4);
+        sets.union(4, b);
+        assert_eq!(sets.size(a), 3);
+    }
+
+    #[test]
+    fn long_chain() {
+        let n = 100_000;
+        let mut sets = UnionFind::new(n);
+        for i in 1..n {
+            sets.union(i - 1, i);
+        }
+        assert_eq!(sets.set_count(), 1);
+        assert_eq!(sets.size(n - 1), n);
+        assert!(sets.connected(0, n - 1));
+        assert_eq!(sets.members(0).len(), n);
+    }
+}